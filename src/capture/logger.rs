@@ -1,15 +1,26 @@
 //! Session logging implementation
 
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::Mutex;
-use tracing::info;
+use tracing::{info, warn};
 
-use super::SessionLog;
+use crate::metrics::AttackerMetrics;
+use super::{CastDirection, NullSink, SessionLog, SessionSink};
+
+/// Default PTY dimensions assumed until a `pty_request` reports the real ones
+const DEFAULT_PTY_SIZE: (u16, u16) = (80, 24);
 
 /// Logger for SSH sessions
 pub struct SessionLogger {
     log: Arc<Mutex<SessionLog>>,
+    started_at: Instant,
+    pty_size: Mutex<(u16, u16)>,
+    metrics: Option<Arc<AttackerMetrics>>,
+    sink: Arc<dyn SessionSink>,
+    casts_dir: Option<PathBuf>,
 }
 
 impl SessionLogger {
@@ -17,9 +28,69 @@ impl SessionLogger {
     pub fn new(addr: Option<SocketAddr>) -> Self {
         Self {
             log: Arc::new(Mutex::new(SessionLog::new(addr))),
+            started_at: Instant::now(),
+            pty_size: Mutex::new(DEFAULT_PTY_SIZE),
+            metrics: None,
+            sink: Arc::new(NullSink),
+            casts_dir: None,
+        }
+    }
+
+    /// Create a new session logger that feeds attacker analytics
+    pub fn with_metrics(addr: Option<SocketAddr>, metrics: Arc<AttackerMetrics>) -> Self {
+        Self {
+            metrics: Some(metrics),
+            ..Self::new(addr)
         }
     }
 
+    /// Create a new session logger that feeds attacker analytics and hands
+    /// finished sessions off to `sink` instead of discarding them
+    pub fn with_metrics_and_sink(
+        addr: Option<SocketAddr>,
+        metrics: Arc<AttackerMetrics>,
+        sink: Arc<dyn SessionSink>,
+    ) -> Self {
+        Self {
+            sink,
+            ..Self::with_metrics(addr, metrics)
+        }
+    }
+
+    /// Create a new session logger that, in addition to analytics and a
+    /// sink, writes each session's recorded terminal I/O to an asciicast
+    /// v2 `.cast` file under `casts_dir` when the session ends
+    pub fn with_metrics_sink_and_casts_dir(
+        addr: Option<SocketAddr>,
+        metrics: Arc<AttackerMetrics>,
+        sink: Arc<dyn SessionSink>,
+        casts_dir: PathBuf,
+    ) -> Self {
+        Self {
+            casts_dir: Some(casts_dir),
+            ..Self::with_metrics_and_sink(addr, metrics, sink)
+        }
+    }
+
+    /// Record the PTY dimensions negotiated for this session
+    pub async fn set_pty_size(&self, cols: u16, rows: u16) {
+        *self.pty_size.lock().await = (cols, rows);
+    }
+
+    /// Record PTY output sent to the client, for asciicast replay
+    pub async fn log_output(&self, data: &[u8]) {
+        let offset = self.started_at.elapsed().as_secs_f64();
+        let mut log = self.log.lock().await;
+        log.add_cast_event(offset, CastDirection::Output, &String::from_utf8_lossy(data));
+    }
+
+    /// Record keystroke input received from the client, for asciicast replay
+    pub async fn log_input(&self, data: &[u8]) {
+        let offset = self.started_at.elapsed().as_secs_f64();
+        let mut log = self.log.lock().await;
+        log.add_cast_event(offset, CastDirection::Input, &String::from_utf8_lossy(data));
+    }
+
     /// Log an authentication attempt
     pub async fn log_auth(&self, username: &str, password: &str, success: bool) {
         let mut log = self.log.lock().await;
@@ -32,6 +103,10 @@ impl SessionLogger {
             success = success,
             "Authentication attempt"
         );
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_auth(username, password).await;
+        }
     }
 
     /// Log a command execution
@@ -78,17 +153,41 @@ impl SessionLogger {
         let mut log = self.log.lock().await;
         log.end();
 
+        if let Some(casts_dir) = &self.casts_dir {
+            let (width, height) = *self.pty_size.lock().await;
+            let cast = log.to_asciicast(width, height);
+            let session_id = log.session_id;
+
+            match write_cast_file(casts_dir, session_id, &cast).await {
+                Ok(path) => log.add_event("cast_recorded", &path.display().to_string()),
+                Err(e) => warn!(session_id = %session_id, error = %e, "Failed to write asciicast file"),
+            }
+        }
+
         info!(
             session_id = %log.session_id,
             duration_seconds = (log.timestamp_end.unwrap() - log.timestamp_start).num_seconds(),
             commands = log.commands.len(),
             downloads = log.downloads.len(),
+            cast_events = log.cast_events.len(),
             "Session ended"
         );
 
-        // TODO: Write to persistent storage
-        // For now, return a clone
-        log.clone()
+        let finished = log.clone();
+        drop(log);
+
+        if let Err(e) = self.sink.write_session(&finished).await {
+            warn!(session_id = %finished.session_id, error = %e, "Failed to write session log");
+        }
+
+        finished
+    }
+
+    /// Render the recorded terminal I/O as an asciicast v2 file
+    pub async fn render_asciicast(&self) -> String {
+        let (width, height) = *self.pty_size.lock().await;
+        let log = self.log.lock().await;
+        log.to_asciicast(width, height)
     }
 
     /// Get the session ID
@@ -104,6 +203,15 @@ impl SessionLogger {
     }
 }
 
+/// Write a session's rendered asciicast to `<casts_dir>/<session_id>.cast`,
+/// creating `casts_dir` if it doesn't exist yet
+async fn write_cast_file(casts_dir: &std::path::Path, session_id: uuid::Uuid, cast: &str) -> crate::Result<PathBuf> {
+    tokio::fs::create_dir_all(casts_dir).await?;
+    let path = casts_dir.join(format!("{}.cast", session_id));
+    tokio::fs::write(&path, cast).await?;
+    Ok(path)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -130,4 +238,73 @@ mod tests {
         let log = logger.get_log().await;
         assert_eq!(log.commands.len(), 1);
     }
+
+    #[tokio::test]
+    async fn test_asciicast_header_and_events() {
+        let logger = SessionLogger::new(None);
+        logger.set_pty_size(120, 40).await;
+        logger.log_output(b"$ ").await;
+        logger.log_input(b"whoami\n").await;
+
+        let cast = logger.render_asciicast().await;
+        let mut lines = cast.lines();
+
+        let header: serde_json::Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+        assert_eq!(header["version"], 2);
+        assert_eq!(header["width"], 120);
+        assert_eq!(header["height"], 40);
+
+        let events: Vec<_> = lines.collect();
+        assert_eq!(events.len(), 2);
+        let first: serde_json::Value = serde_json::from_str(events[0]).unwrap();
+        assert_eq!(first[1], "o");
+        let second: serde_json::Value = serde_json::from_str(events[1]).unwrap();
+        assert_eq!(second[1], "i");
+    }
+
+    #[tokio::test]
+    async fn test_end_session_writes_cast_file_and_records_its_path() {
+        let dir = tempfile::TempDir::new().unwrap();
+
+        let logger = SessionLogger::with_metrics_sink_and_casts_dir(
+            None,
+            Arc::new(AttackerMetrics::new()),
+            Arc::new(NullSink),
+            dir.path().to_path_buf(),
+        );
+        logger.log_output(b"$ ").await;
+        logger.log_input(b"whoami\n").await;
+        let session_id = logger.session_id().await;
+        let finished = logger.end_session().await;
+
+        let cast_path = dir.path().join(format!("{}.cast", session_id));
+        let cast = tokio::fs::read_to_string(&cast_path).await.unwrap();
+        assert!(cast.lines().count() >= 3); // header + 2 events
+
+        assert!(finished
+            .events
+            .iter()
+            .any(|e| e.event_type == "cast_recorded" && e.data == cast_path.display().to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_end_session_writes_to_configured_sink() {
+        use super::super::JsonlSink;
+        use crate::metrics::AttackerMetrics;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("sessions.jsonl");
+        let sink = Arc::new(JsonlSink::new(path.clone()));
+
+        let logger = SessionLogger::with_metrics_and_sink(
+            None,
+            Arc::new(AttackerMetrics::new()),
+            sink,
+        );
+        logger.log_command("whoami", "root\n").await;
+        logger.end_session().await;
+
+        let contents = tokio::fs::read_to_string(&path).await.unwrap();
+        assert_eq!(contents.lines().count(), 1);
+    }
 }
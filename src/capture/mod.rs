@@ -1,9 +1,13 @@
 //! Data capture and logging module
 
 mod logger;
+mod postgres_sink;
+mod sink;
 mod storage;
 
 pub use logger::SessionLogger;
+pub use postgres_sink::PostgresSink;
+pub use sink::{BincodeSink, BroadcastSink, BrokerSink, CompositeSink, JsonlSink, NullSink, SessionSink};
 pub use storage::FileStorage;
 
 use chrono::{DateTime, Utc};
@@ -50,6 +54,7 @@ pub struct SessionLog {
     pub commands: Vec<CommandExecution>,
     pub downloads: Vec<FileDownload>,
     pub events: Vec<SessionEvent>,
+    pub cast_events: Vec<CastEvent>,
 }
 
 /// Generic session event
@@ -60,6 +65,35 @@ pub struct SessionEvent {
     pub data: String,
 }
 
+/// Direction of a captured terminal I/O event, matching asciicast's `"o"`/`"i"` markers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CastDirection {
+    /// Bytes sent from the honeypot to the client (PTY output)
+    Output,
+    /// Bytes sent from the client to the honeypot (keystrokes)
+    Input,
+}
+
+impl CastDirection {
+    fn as_code(self) -> &'static str {
+        match self {
+            CastDirection::Output => "o",
+            CastDirection::Input => "i",
+        }
+    }
+}
+
+/// A single terminal I/O event, timestamped relative to session start
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CastEvent {
+    /// Seconds since the recording started
+    pub offset_secs: f64,
+    /// Whether this was output or input
+    pub direction: CastDirection,
+    /// The captured bytes, lossily decoded to UTF-8
+    pub data: String,
+}
+
 impl SessionLog {
     /// Create a new session log
     pub fn new(addr: Option<SocketAddr>) -> Self {
@@ -77,6 +111,7 @@ impl SessionLog {
             commands: Vec::new(),
             downloads: Vec::new(),
             events: Vec::new(),
+            cast_events: Vec::new(),
         }
     }
 
@@ -123,6 +158,40 @@ impl SessionLog {
             data: data.to_string(),
         });
     }
+
+    /// Record a terminal I/O event for asciicast replay
+    pub fn add_cast_event(&mut self, offset_secs: f64, direction: CastDirection, data: &str) {
+        self.cast_events.push(CastEvent {
+            offset_secs,
+            direction,
+            data: data.to_string(),
+        });
+    }
+
+    /// Render the recorded terminal events as an asciicast v2 file (newline-delimited JSON)
+    pub fn to_asciicast(&self, width: u16, height: u16) -> String {
+        let header = serde_json::json!({
+            "version": 2,
+            "width": width,
+            "height": height,
+            "timestamp": self.timestamp_start.timestamp(),
+            "env": {
+                "SHELL": "/bin/bash",
+                "TERM": "xterm-256color",
+            },
+        });
+
+        let mut out = header.to_string();
+        out.push('\n');
+
+        for event in &self.cast_events {
+            let line = serde_json::json!([event.offset_secs, event.direction.as_code(), event.data]);
+            out.push_str(&line.to_string());
+            out.push('\n');
+        }
+
+        out
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,307 @@
+//! Pluggable destinations for finished session logs
+
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast;
+use tracing::info;
+
+use crate::Result;
+use super::SessionLog;
+
+/// How long a publish to an external broker endpoint is allowed to run
+/// before being abandoned
+const BROKER_PUBLISH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Destination a completed `SessionLog` is handed off to
+#[async_trait]
+pub trait SessionSink: Send + Sync {
+    /// Persist or publish a finished session
+    async fn write_session(&self, log: &SessionLog) -> Result<()>;
+}
+
+/// Discards sessions; used when persistent storage is disabled
+pub struct NullSink;
+
+#[async_trait]
+impl SessionSink for NullSink {
+    async fn write_session(&self, _log: &SessionLog) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Appends each finished session as a single JSON-lines record
+pub struct JsonlSink {
+    path: PathBuf,
+}
+
+impl JsonlSink {
+    /// Create a sink that appends to `path`, creating its parent directory
+    /// and the file itself on first write
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+#[async_trait]
+impl SessionSink for JsonlSink {
+    async fn write_session(&self, log: &SessionLog) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+
+        let mut line = serde_json::to_string(log)?;
+        line.push('\n');
+        file.write_all(line.as_bytes()).await?;
+
+        info!(session_id = %log.session_id, path = ?self.path, "Wrote session log");
+        Ok(())
+    }
+}
+
+/// Appends each finished session as a length-prefixed `bincode` record,
+/// more compact on disk and cheaper to encode/decode than `JsonlSink`'s
+/// JSON lines for deployments logging a high volume of sessions
+pub struct BincodeSink {
+    path: PathBuf,
+}
+
+impl BincodeSink {
+    /// Create a sink that appends to `path`, creating its parent directory
+    /// and the file itself on first write
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+#[async_trait]
+impl SessionSink for BincodeSink {
+    async fn write_session(&self, log: &SessionLog) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let encoded = bincode::serialize(log)?;
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+
+        file.write_all(&(encoded.len() as u32).to_le_bytes()).await?;
+        file.write_all(&encoded).await?;
+
+        info!(session_id = %log.session_id, path = ?self.path, "Wrote session log");
+        Ok(())
+    }
+}
+
+/// Publishes each finished session to any live subscribers (e.g. a
+/// real-time dashboard) instead of writing it to disk
+pub struct BroadcastSink {
+    tx: broadcast::Sender<SessionLog>,
+}
+
+impl BroadcastSink {
+    /// Create a sink with room for `capacity` unread sessions per subscriber
+    /// before the oldest is dropped
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity);
+        Self { tx }
+    }
+
+    /// Subscribe to the stream of finished sessions
+    pub fn subscribe(&self) -> broadcast::Receiver<SessionLog> {
+        self.tx.subscribe()
+    }
+}
+
+#[async_trait]
+impl SessionSink for BroadcastSink {
+    async fn write_session(&self, log: &SessionLog) -> Result<()> {
+        // No subscribers is a normal, not an error, condition
+        let _ = self.tx.send(log.clone());
+        Ok(())
+    }
+}
+
+/// Publishes each finished session as a JSON message over HTTP to an
+/// external broker endpoint (a SIEM's HTTP event collector, a webhook-style
+/// ingest queue, etc.), for real-time ingestion outside this process
+pub struct BrokerSink {
+    client: reqwest::Client,
+    endpoint: String,
+}
+
+impl BrokerSink {
+    /// Create a sink that POSTs each session as JSON to `endpoint`
+    pub fn new(endpoint: impl Into<String>) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(BROKER_PUBLISH_TIMEOUT)
+            .build()?;
+        Ok(Self { client, endpoint: endpoint.into() })
+    }
+}
+
+#[async_trait]
+impl SessionSink for BrokerSink {
+    async fn write_session(&self, log: &SessionLog) -> Result<()> {
+        let response = self.client.post(&self.endpoint).json(log).send().await?;
+
+        if !response.status().is_success() {
+            return Err(format!("broker endpoint {} returned {}", self.endpoint, response.status()).into());
+        }
+
+        info!(session_id = %log.session_id, endpoint = %self.endpoint, "Published session log to broker");
+        Ok(())
+    }
+}
+
+/// Fans a finished session out to several sinks at once, so e.g. a JSON file
+/// and a database can both be enabled at the same time
+pub struct CompositeSink {
+    sinks: Vec<std::sync::Arc<dyn SessionSink>>,
+}
+
+impl CompositeSink {
+    /// Create a sink that forwards every session to each of `sinks`
+    pub fn new(sinks: Vec<std::sync::Arc<dyn SessionSink>>) -> Self {
+        Self { sinks }
+    }
+}
+
+#[async_trait]
+impl SessionSink for CompositeSink {
+    async fn write_session(&self, log: &SessionLog) -> Result<()> {
+        // Keep writing to every sink even if one fails; log failures rather
+        // than letting one bad destination swallow the rest.
+        for sink in &self.sinks {
+            if let Err(e) = sink.write_session(log).await {
+                tracing::warn!(session_id = %log.session_id, error = %e, "Sink failed to write session");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use tempfile::TempDir;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn test_jsonl_sink_appends_one_line_per_session() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("sessions.jsonl");
+        let sink = JsonlSink::new(path.clone());
+
+        let addr: SocketAddr = "127.0.0.1:2222".parse().unwrap();
+        sink.write_session(&SessionLog::new(Some(addr))).await.unwrap();
+        sink.write_session(&SessionLog::new(Some(addr))).await.unwrap();
+
+        let contents = tokio::fs::read_to_string(&path).await.unwrap();
+        assert_eq!(contents.lines().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_bincode_sink_appends_one_record_per_session() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("sessions.bin");
+        let sink = BincodeSink::new(path.clone());
+
+        let log = SessionLog::new(None);
+        sink.write_session(&log).await.unwrap();
+        sink.write_session(&log).await.unwrap();
+
+        let contents = tokio::fs::read(&path).await.unwrap();
+        let mut cursor = contents.as_slice();
+        let mut records = 0;
+        while !cursor.is_empty() {
+            let len = u32::from_le_bytes(cursor[..4].try_into().unwrap()) as usize;
+            let decoded: SessionLog = bincode::deserialize(&cursor[4..4 + len]).unwrap();
+            assert_eq!(decoded.session_id, log.session_id);
+            cursor = &cursor[4 + len..];
+            records += 1;
+        }
+        assert_eq!(records, 2);
+    }
+
+    #[tokio::test]
+    async fn test_broker_sink_publishes_session_as_json_over_http() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let n = stream.read(&mut buf).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..n]).to_string();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            request.contains("POST")
+        });
+
+        let sink = BrokerSink::new(format!("http://{}/sessions", addr)).unwrap();
+        sink.write_session(&SessionLog::new(None)).await.unwrap();
+
+        assert!(server.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_broker_sink_reports_error_on_unreachable_endpoint() {
+        let sink = BrokerSink::new("http://127.0.0.1:1/sessions").unwrap();
+        let result = sink.write_session(&SessionLog::new(None)).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_null_sink_discards() {
+        let sink = NullSink;
+        assert!(sink.write_session(&SessionLog::new(None)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_sink_reaches_subscriber() {
+        let sink = BroadcastSink::new(4);
+        let mut rx = sink.subscribe();
+
+        let log = SessionLog::new(None);
+        sink.write_session(&log).await.unwrap();
+
+        let received = rx.recv().await.unwrap();
+        assert_eq!(received.session_id, log.session_id);
+    }
+
+    #[tokio::test]
+    async fn test_composite_sink_forwards_to_every_sink() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("sessions.jsonl");
+
+        let broadcast = std::sync::Arc::new(BroadcastSink::new(4));
+        let mut rx = broadcast.subscribe();
+
+        let composite = CompositeSink::new(vec![
+            std::sync::Arc::new(JsonlSink::new(path.clone())),
+            broadcast,
+        ]);
+
+        composite.write_session(&SessionLog::new(None)).await.unwrap();
+
+        let contents = tokio::fs::read_to_string(&path).await.unwrap();
+        assert_eq!(contents.lines().count(), 1);
+        assert!(rx.recv().await.is_ok());
+    }
+}
@@ -8,8 +8,8 @@ impl Default for ServerConfig {
             listen_addr: "0.0.0.0".to_string(),
             port: 2222,
             max_connections: 100,
-            session_timeout_secs: 1800, // 30 minutes
-            auth_delay_secs: 2,
+            session_timeout_secs: HumanDuration::from_secs(1800), // 30 minutes
+            auth_delay_secs: HumanDuration::from_secs(2),
         }
     }
 }
@@ -19,9 +19,21 @@ impl Default for SecurityConfig {
         Self {
             rate_limit_enabled: true,
             max_connections_per_ip: 10,
-            rate_limit_window_secs: 60,
+            rate_limit_window_secs: HumanDuration::from_secs(60),
             whitelist_ips: Vec::new(),
             blacklist_ips: Vec::new(),
+            blocklist_backend: default_blocklist_backend(),
+            nft_table: default_nft_table(),
+            nft_set: default_nft_set(),
+            ipset_name: default_ipset_name(),
+            blocklist_file_path: None,
+            ban_after_violations: default_ban_after_violations(),
+            ban_persist_path: None,
+            offender_window_secs: default_offender_window_secs(),
+            offender_auth_threshold: default_offender_auth_threshold(),
+            offender_command_threshold: default_offender_command_threshold(),
+            offender_ban_ttl_secs: default_offender_ban_ttl_secs(),
+            tarpit_delay_secs: default_tarpit_delay_secs(),
         }
     }
 }
@@ -33,6 +45,7 @@ impl Default for LoggingConfig {
             format: "json".to_string(),
             output: "stdout".to_string(),
             file_path: None,
+            facility: default_syslog_facility(),
         }
     }
 }
@@ -43,6 +56,8 @@ impl Default for StorageConfig {
             enabled: true,
             backend: "file".to_string(),
             file: Some(FileStorageConfig::default()),
+            postgres: None,
+            broker: None,
         }
     }
 }
@@ -53,6 +68,7 @@ impl Default for FileStorageConfig {
             base_path: "./data".to_string(),
             sessions_dir: "./data/sessions".to_string(),
             files_dir: "./data/captured_files".to_string(),
+            casts_dir: "./data/casts".to_string(),
         }
     }
 }
@@ -65,14 +81,25 @@ impl Default for ShellConfig {
             max_history: 1000,
             banner: "Welcome to Ubuntu 22.04.1 LTS (GNU/Linux 5.15.0-58-generic x86_64)\n\n\
                      Last login: Sat Nov  9 10:30:15 2025 from 192.168.1.1\n".to_string(),
+            personalities: default_personalities(),
+            filesystem: FilesystemProfile::default(),
         }
     }
 }
 
+impl Default for Personality {
+    fn default() -> Self {
+        default_personalities()
+            .into_iter()
+            .next()
+            .expect("default personality pool is never empty")
+    }
+}
+
 impl Default for CaptureConfig {
     fn default() -> Self {
         Self {
-            capture_downloads: true,
+            capture_downloads: false,
             max_file_size_bytes: 10 * 1024 * 1024, // 10MB
         }
     }
@@ -95,6 +122,9 @@ mod tests {
         let cfg = SecurityConfig::default();
         assert!(cfg.rate_limit_enabled);
         assert_eq!(cfg.max_connections_per_ip, 10);
+        assert_eq!(cfg.offender_window_secs.as_secs(), 300);
+        assert_eq!(cfg.offender_auth_threshold, 5);
+        assert_eq!(cfg.offender_command_threshold, 50);
     }
 
     #[test]
@@ -119,12 +149,20 @@ mod tests {
         assert_eq!(cfg.hostname, "honeypot");
         assert!(cfg.history_enabled);
         assert_eq!(cfg.max_history, 1000);
+        assert!(!cfg.personalities.is_empty());
+    }
+
+    #[test]
+    fn test_personality_pool_has_distinct_hostnames() {
+        let pool = default_personalities();
+        let hostnames: std::collections::HashSet<_> = pool.iter().map(|p| &p.hostname).collect();
+        assert_eq!(hostnames.len(), pool.len());
     }
 
     #[test]
     fn test_capture_defaults() {
         let cfg = CaptureConfig::default();
-        assert!(cfg.capture_downloads);
+        assert!(!cfg.capture_downloads);
         assert_eq!(cfg.max_file_size_bytes, 10 * 1024 * 1024);
     }
 }
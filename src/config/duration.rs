@@ -0,0 +1,209 @@
+//! Human-readable durations for `*_secs` config fields
+//!
+//! TOML has no native duration type, so these fields are easy to get wrong
+//! ("is that minutes or seconds?"). `HumanDuration` accepts either a bare
+//! integer (seconds, for backward compatibility with existing configs) or a
+//! suffixed string such as `"30s"`, `"5m"`, `"2h"`, or `"1d"`, and always
+//! serializes back out in the suffixed form.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A config duration, stored internally as whole seconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HumanDuration(u64);
+
+impl HumanDuration {
+    /// Construct a duration from a number of seconds
+    pub fn from_secs(secs: u64) -> Self {
+        Self(secs)
+    }
+
+    /// The duration in whole seconds
+    pub fn as_secs(self) -> u64 {
+        self.0
+    }
+}
+
+impl FromStr for HumanDuration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("duration string cannot be empty".to_string());
+        }
+
+        // A bare integer means seconds, same as the plain-number form
+        if let Ok(secs) = s.parse::<u64>() {
+            return Ok(Self(secs));
+        }
+
+        let (digits, multiplier) = match s.split_at(s.len() - 1) {
+            (digits, "s") => (digits, 1u64),
+            (digits, "m") => (digits, 60),
+            (digits, "h") => (digits, 3600),
+            (digits, "d") => (digits, 86400),
+            _ => return Err(format!("invalid duration {:?}: expected a suffix of s, m, h, or d", s)),
+        };
+
+        if digits.is_empty() {
+            return Err(format!("invalid duration {:?}: missing a number before the suffix", s));
+        }
+
+        let amount: u64 = digits
+            .parse()
+            .map_err(|_| format!("invalid duration {:?}: {:?} is not a whole number", s, digits))?;
+
+        amount
+            .checked_mul(multiplier)
+            .map(Self)
+            .ok_or_else(|| format!("duration {:?} overflows u64 seconds", s))
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    /// The canonical suffixed form: the largest unit that evenly divides the
+    /// duration, e.g. 90 -> "90s", 120 -> "2m", 7200 -> "2h", 86400 -> "1d"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0;
+        if secs != 0 && secs % 86400 == 0 {
+            write!(f, "{}d", secs / 86400)
+        } else if secs != 0 && secs % 3600 == 0 {
+            write!(f, "{}h", secs / 3600)
+        } else if secs != 0 && secs % 60 == 0 {
+            write!(f, "{}m", secs / 60)
+        } else {
+            write!(f, "{}s", secs)
+        }
+    }
+}
+
+struct HumanDurationVisitor;
+
+impl<'de> Visitor<'de> for HumanDurationVisitor {
+    type Value = HumanDuration;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a number of seconds, or a string like \"30s\", \"5m\", \"2h\", \"1d\"")
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(HumanDuration(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        u64::try_from(value)
+            .map(HumanDuration)
+            .map_err(|_| E::custom(format!("duration {} cannot be negative", value)))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        value.parse().map_err(E::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for HumanDuration {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(HumanDurationVisitor)
+    }
+}
+
+impl Serialize for HumanDuration {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plain_integer_as_seconds() {
+        assert_eq!("45".parse::<HumanDuration>().unwrap().as_secs(), 45);
+    }
+
+    #[test]
+    fn test_parse_seconds_suffix() {
+        assert_eq!("30s".parse::<HumanDuration>().unwrap().as_secs(), 30);
+    }
+
+    #[test]
+    fn test_parse_minutes_suffix() {
+        assert_eq!("5m".parse::<HumanDuration>().unwrap().as_secs(), 300);
+    }
+
+    #[test]
+    fn test_parse_hours_suffix() {
+        assert_eq!("2h".parse::<HumanDuration>().unwrap().as_secs(), 7200);
+    }
+
+    #[test]
+    fn test_parse_days_suffix() {
+        assert_eq!("1d".parse::<HumanDuration>().unwrap().as_secs(), 86400);
+    }
+
+    #[test]
+    fn test_parse_empty_string_rejected() {
+        assert!("".parse::<HumanDuration>().is_err());
+    }
+
+    #[test]
+    fn test_parse_unknown_suffix_rejected() {
+        assert!("10x".parse::<HumanDuration>().is_err());
+    }
+
+    #[test]
+    fn test_parse_missing_number_rejected() {
+        assert!("m".parse::<HumanDuration>().is_err());
+    }
+
+    #[test]
+    fn test_parse_overflow_rejected() {
+        assert!(format!("{}d", u64::MAX).parse::<HumanDuration>().is_err());
+    }
+
+    #[test]
+    fn test_display_canonical_form() {
+        assert_eq!(HumanDuration::from_secs(90).to_string(), "90s");
+        assert_eq!(HumanDuration::from_secs(120).to_string(), "2m");
+        assert_eq!(HumanDuration::from_secs(7200).to_string(), "2h");
+        assert_eq!(HumanDuration::from_secs(86400).to_string(), "1d");
+        assert_eq!(HumanDuration::from_secs(0).to_string(), "0s");
+    }
+
+    #[test]
+    fn test_deserialize_from_toml_integer_and_string() {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            d: HumanDuration,
+        }
+
+        let from_int: Wrapper = toml::from_str("d = 30").unwrap();
+        assert_eq!(from_int.d.as_secs(), 30);
+
+        let from_str: Wrapper = toml::from_str("d = \"5m\"").unwrap();
+        assert_eq!(from_str.d.as_secs(), 300);
+    }
+
+    #[test]
+    fn test_serialize_round_trips_through_canonical_form() {
+        #[derive(Serialize, Deserialize)]
+        struct Wrapper {
+            d: HumanDuration,
+        }
+
+        let wrapper = Wrapper { d: HumanDuration::from_secs(3600) };
+        let toml_str = toml::to_string(&wrapper).unwrap();
+        assert_eq!(toml_str.trim(), r#"d = "1h""#);
+    }
+}
@@ -2,9 +2,23 @@
 
 use super::*;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use tokio::sync::watch;
 use tracing::{info, warn};
 
+/// Command-line overrides layered on top of a loaded `Config`, the
+/// highest-precedence tier in the chain CLI args > environment > file >
+/// `Config::default`
+#[derive(Debug, Default, Clone)]
+pub struct CliOverrides {
+    /// Overrides `server.port`
+    pub port: Option<u16>,
+    /// Overrides `server.listen_addr`
+    pub listen_addr: Option<String>,
+    /// Overrides `logging.level`
+    pub log_level: Option<String>,
+}
+
 /// Configuration loader
 pub struct ConfigLoader {
     search_paths: Vec<PathBuf>,
@@ -65,6 +79,160 @@ impl ConfigLoader {
         Ok(config)
     }
 
+    /// Load configuration from `explicit_path` if given, otherwise the first
+    /// found search-path file, then layer `overrides` on top so CLI args
+    /// always win over both the file and `DRHPOTTER_*` environment variables
+    pub fn load_with_overrides(&self, explicit_path: Option<PathBuf>, overrides: &CliOverrides) -> Result<Config> {
+        let mut config = match explicit_path {
+            Some(path) => self.from_file(path)?,
+            None => self.load()?,
+        };
+
+        self.apply_cli_overrides(&mut config, overrides);
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Watch `path` for changes, re-parsing and validating the file on every
+    /// write and publishing the result through a `tokio::sync::watch`
+    /// channel. Invalid reloads are logged as a warning and the previously
+    /// published config is kept; `server.listen_addr`/`port` changes are
+    /// logged as deferred to the next restart rather than applied live.
+    pub fn watch(&self, path: PathBuf) -> Result<watch::Receiver<Config>> {
+        let initial = self.from_file(path.clone())?;
+        let (tx, rx) = watch::channel(initial);
+        let loader = ConfigLoader::new();
+
+        tokio::spawn(async move {
+            let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel(16);
+
+            let mut watcher = match notify::recommended_watcher(move |res| {
+                let _ = notify_tx.blocking_send(res);
+            }) {
+                Ok(watcher) => watcher,
+                Err(e) => {
+                    warn!("Failed to start config watcher for {:?}: {}", path, e);
+                    return;
+                }
+            };
+
+            if let Err(e) = notify::Watcher::watch(&mut watcher, &path, notify::RecursiveMode::NonRecursive) {
+                warn!("Failed to watch config file {:?}: {}", path, e);
+                return;
+            }
+
+            while let Some(event) = notify_rx.recv().await {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(e) => {
+                        warn!("Config watcher error for {:?}: {}", path, e);
+                        continue;
+                    }
+                };
+
+                if !event.kind.is_modify() && !event.kind.is_create() {
+                    continue;
+                }
+
+                match loader.from_file(path.clone()) {
+                    Ok(new_config) => {
+                        let previous = tx.borrow().clone();
+                        if previous.server.listen_addr != new_config.server.listen_addr
+                            || previous.server.port != new_config.server.port
+                        {
+                            warn!(
+                                "server.listen_addr/port changed in {:?}; restart required to apply",
+                                path
+                            );
+                        }
+
+                        info!("Reloaded configuration from {:?}", path);
+                        let _ = tx.send(new_config);
+                    }
+                    Err(e) => {
+                        warn!("Ignoring invalid config reload from {:?}: {}", path, e);
+                    }
+                }
+            }
+        });
+
+        Ok(rx)
+    }
+
+    /// Walk the search paths in priority order, write a fully-populated,
+    /// commented default configuration to the first one whose parent
+    /// directory exists or can be created and is writable, and return the
+    /// path written. Refuses to overwrite an existing file unless `force`
+    /// is `true`.
+    pub fn init_default(&self, force: bool) -> Result<PathBuf> {
+        for path in &self.search_paths {
+            let parent = match path.parent() {
+                Some(p) if !p.as_os_str().is_empty() => p,
+                _ => Path::new("."),
+            };
+
+            if fs::create_dir_all(parent).is_err() {
+                continue;
+            }
+
+            if !Self::is_writable(parent) {
+                continue;
+            }
+
+            if path.exists() && !force {
+                return Err(format!(
+                    "Refusing to overwrite existing config file {:?} (use --force to overwrite)",
+                    path
+                )
+                .into());
+            }
+
+            fs::write(path, self.render_default_toml()?)
+                .map_err(|e| format!("Failed to write default config to {:?}: {}", path, e))?;
+
+            info!("Wrote default configuration to {:?}", path);
+            return Ok(path.clone());
+        }
+
+        Err("No writable location found among the configured search paths".into())
+    }
+
+    /// Probe `dir` for writability by attempting (and immediately cleaning
+    /// up) a throwaway file; this is the only portable way to check without
+    /// relying on platform-specific permission bits
+    fn is_writable(dir: &Path) -> bool {
+        let probe = dir.join(".drhpotter-write-test");
+        match fs::write(&probe, b"") {
+            Ok(()) => {
+                let _ = fs::remove_file(&probe);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Render `Config::default` as a commented TOML file: a header
+    /// explaining where it came from, followed by the plain serialized
+    /// defaults
+    fn render_default_toml(&self) -> Result<String> {
+        let config = Config::default();
+        let body = toml::to_string_pretty(&config)
+            .map_err(|e| format!("Failed to render default configuration: {}", e))?;
+
+        Ok(format!(
+            "# DrHPotter default configuration\n\
+             #\n\
+             # Generated by `drhpotter --generate-config`. Every value below is\n\
+             # the built-in default; edit freely, then restart (or rely on\n\
+             # hot-reload when --config points at this file).\n\
+             #\n\
+             # Search order when --config isn't passed: {:?}\n\
+             \n{}",
+            self.search_paths, body
+        ))
+    }
+
     /// Apply environment variable overrides
     fn apply_env_overrides(&self, config: &mut Config) {
         // Server overrides
@@ -91,6 +259,24 @@ impl ConfigLoader {
             config.logging.format = format;
         }
     }
+
+    /// Apply command-line overrides, the highest-precedence tier
+    fn apply_cli_overrides(&self, config: &mut Config, overrides: &CliOverrides) {
+        if let Some(port) = overrides.port {
+            info!("Overriding server port from CLI: {}", port);
+            config.server.port = port;
+        }
+
+        if let Some(addr) = &overrides.listen_addr {
+            info!("Overriding listen address from CLI: {}", addr);
+            config.server.listen_addr = addr.clone();
+        }
+
+        if let Some(level) = &overrides.log_level {
+            info!("Overriding log level from CLI: {}", level);
+            config.logging.level = level.clone();
+        }
+    }
 }
 
 impl Default for ConfigLoader {
@@ -291,4 +477,241 @@ max_file_size_bytes = 10485760
 
         std::env::remove_var("DRHPOTTER_SERVER_PORT");
     }
+
+    #[tokio::test]
+    async fn test_watch_publishes_initial_config() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            r#"
+[server]
+listen_addr = "0.0.0.0"
+port = 2222
+max_connections = 100
+session_timeout_secs = 1800
+auth_delay_secs = 2
+
+[security]
+rate_limit_enabled = true
+max_connections_per_ip = 10
+rate_limit_window_secs = 60
+whitelist_ips = []
+blacklist_ips = []
+
+[logging]
+level = "info"
+format = "json"
+output = "stdout"
+
+[storage]
+enabled = true
+backend = "file"
+
+[storage.file]
+base_path = "./data"
+sessions_dir = "./data/sessions"
+files_dir = "./data/captured_files"
+
+[shell]
+hostname = "honeypot"
+history_enabled = true
+max_history = 1000
+banner = "Test\n"
+
+[capture]
+capture_downloads = true
+max_file_size_bytes = 10485760
+"#
+        )
+        .unwrap();
+
+        let loader = ConfigLoader::new();
+        let rx = loader.watch(file.path().to_path_buf()).unwrap();
+
+        assert_eq!(rx.borrow().server.port, 2222);
+    }
+
+    #[test]
+    fn test_load_with_overrides_falls_back_to_defaults() {
+        let loader = ConfigLoader::new();
+        let overrides = CliOverrides {
+            port: Some(5555),
+            ..Default::default()
+        };
+
+        // No explicit path and (presumably) no search-path file on a clean
+        // test environment, so this exercises CLI-over-default precedence.
+        let config = loader.load_with_overrides(None, &overrides).unwrap();
+        assert_eq!(config.server.port, 5555);
+    }
+
+    #[test]
+    fn test_load_with_overrides_file_beats_default_when_no_cli_override() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            r#"
+[server]
+listen_addr = "0.0.0.0"
+port = 3333
+max_connections = 100
+session_timeout_secs = 1800
+auth_delay_secs = 2
+
+[security]
+rate_limit_enabled = true
+max_connections_per_ip = 10
+rate_limit_window_secs = 60
+whitelist_ips = []
+blacklist_ips = []
+
+[logging]
+level = "info"
+format = "json"
+output = "stdout"
+
+[storage]
+enabled = true
+backend = "file"
+
+[storage.file]
+base_path = "./data"
+sessions_dir = "./data/sessions"
+files_dir = "./data/captured_files"
+
+[shell]
+hostname = "honeypot"
+history_enabled = true
+max_history = 1000
+banner = "Test\n"
+
+[capture]
+capture_downloads = true
+max_file_size_bytes = 10485760
+"#
+        )
+        .unwrap();
+
+        let loader = ConfigLoader::new();
+        let config = loader
+            .load_with_overrides(Some(file.path().to_path_buf()), &CliOverrides::default())
+            .unwrap();
+        assert_eq!(config.server.port, 3333);
+    }
+
+    #[test]
+    fn test_load_with_overrides_cli_beats_env_and_file() {
+        std::env::set_var("DRHPOTTER_SERVER_PORT", "4444");
+
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            r#"
+[server]
+listen_addr = "0.0.0.0"
+port = 2222
+max_connections = 100
+session_timeout_secs = 1800
+auth_delay_secs = 2
+
+[security]
+rate_limit_enabled = true
+max_connections_per_ip = 10
+rate_limit_window_secs = 60
+whitelist_ips = []
+blacklist_ips = []
+
+[logging]
+level = "info"
+format = "json"
+output = "stdout"
+
+[storage]
+enabled = true
+backend = "file"
+
+[storage.file]
+base_path = "./data"
+sessions_dir = "./data/sessions"
+files_dir = "./data/captured_files"
+
+[shell]
+hostname = "honeypot"
+history_enabled = true
+max_history = 1000
+banner = "Test\n"
+
+[capture]
+capture_downloads = true
+max_file_size_bytes = 10485760
+"#
+        )
+        .unwrap();
+
+        let loader = ConfigLoader::new();
+        let overrides = CliOverrides {
+            port: Some(5555),
+            ..Default::default()
+        };
+        let config = loader
+            .load_with_overrides(Some(file.path().to_path_buf()), &overrides)
+            .unwrap();
+
+        // CLI (5555) beats environment (4444) and file (2222)
+        assert_eq!(config.server.port, 5555);
+
+        std::env::remove_var("DRHPOTTER_SERVER_PORT");
+    }
+
+    #[test]
+    fn test_init_default_writes_commented_toml() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("drhpotter.toml");
+        let loader = ConfigLoader {
+            search_paths: vec![path.clone()],
+        };
+
+        let written = loader.init_default(false).unwrap();
+        assert_eq!(written, path);
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with("# DrHPotter default configuration"));
+
+        // The generated file should itself parse back into a valid config
+        let config = loader.from_file(path).unwrap();
+        assert_eq!(config.server.port, 2222);
+    }
+
+    #[test]
+    fn test_init_default_refuses_to_overwrite_without_force() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("drhpotter.toml");
+        fs::write(&path, "# pre-existing\n").unwrap();
+
+        let loader = ConfigLoader {
+            search_paths: vec![path.clone()],
+        };
+
+        assert!(loader.init_default(false).is_err());
+        // Untouched
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# pre-existing\n");
+
+        assert!(loader.init_default(true).is_ok());
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with("# DrHPotter default configuration"));
+    }
+
+    #[test]
+    fn test_init_default_falls_through_to_next_writable_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let unwritable = PathBuf::from("/proc/drhpotter-cant-create/config.toml");
+        let writable = dir.path().join("drhpotter.toml");
+
+        let loader = ConfigLoader {
+            search_paths: vec![unwritable, writable.clone()],
+        };
+
+        let written = loader.init_default(false).unwrap();
+        assert_eq!(written, writable);
+    }
 }
@@ -46,7 +46,7 @@ impl Validator {
             if config.max_connections_per_ip == 0 {
                 return Err("max_connections_per_ip must be greater than 0".into());
             }
-            if config.rate_limit_window_secs == 0 {
+            if config.rate_limit_window_secs.as_secs() == 0 {
                 return Err("rate_limit_window_secs must be greater than 0".into());
             }
         }
@@ -63,6 +63,45 @@ impl Validator {
                 .map_err(|_| format!("Invalid blacklist IP: {}", ip))?;
         }
 
+        // Validate blocklist backend
+        let valid_backends = ["memory", "nft", "ipset", "file"];
+        if !valid_backends.contains(&config.blocklist_backend.as_str()) {
+            return Err(format!(
+                "Invalid blocklist backend: {} (must be one of: {})",
+                config.blocklist_backend,
+                valid_backends.join(", ")
+            )
+            .into());
+        }
+
+        if config.blocklist_backend == "nft" {
+            if config.nft_table.is_empty() {
+                return Err("nft_table must be set when blocklist_backend is 'nft'".into());
+            }
+            if config.nft_set.is_empty() {
+                return Err("nft_set must be set when blocklist_backend is 'nft'".into());
+            }
+        }
+
+        if config.blocklist_backend == "ipset" && config.ipset_name.is_empty() {
+            return Err("ipset_name must be set when blocklist_backend is 'ipset'".into());
+        }
+
+        if config.blocklist_backend == "file" && config.blocklist_file_path.is_none() {
+            return Err("blocklist_file_path must be set when blocklist_backend is 'file'".into());
+        }
+
+        // Validate offender tracking thresholds
+        if config.offender_window_secs.as_secs() == 0 {
+            return Err("offender_window_secs must be greater than 0".into());
+        }
+        if config.offender_auth_threshold == 0 {
+            return Err("offender_auth_threshold must be greater than 0".into());
+        }
+        if config.offender_command_threshold == 0 {
+            return Err("offender_command_threshold must be greater than 0".into());
+        }
+
         Ok(())
     }
 
@@ -90,7 +129,7 @@ impl Validator {
         }
 
         // Validate log output
-        let valid_outputs = ["stdout", "file"];
+        let valid_outputs = ["stdout", "file", "syslog"];
         if !valid_outputs.contains(&config.output.as_str()) {
             return Err(format!(
                 "Invalid log output: {} (must be one of: {})",
@@ -105,13 +144,32 @@ impl Validator {
             return Err("file_path must be set when output is 'file'".into());
         }
 
+        if config.output == "syslog" {
+            if !cfg!(unix) {
+                return Err("syslog output is only supported on Unix".into());
+            }
+
+            let valid_facilities = [
+                "daemon", "user", "cron", "authpriv", "local0", "local1", "local2", "local3",
+                "local4", "local5", "local6", "local7",
+            ];
+            if !valid_facilities.contains(&config.facility.as_str()) {
+                return Err(format!(
+                    "Invalid syslog facility: {} (must be one of: {})",
+                    config.facility,
+                    valid_facilities.join(", ")
+                )
+                .into());
+            }
+        }
+
         Ok(())
     }
 
     fn validate_storage(&self, config: &StorageConfig) -> Result<()> {
         if config.enabled {
             // Validate backend
-            let valid_backends = ["file", "sqlite"];
+            let valid_backends = ["file", "bincode", "sqlite", "postgres", "broker"];
             if !valid_backends.contains(&config.backend.as_str()) {
                 return Err(format!(
                     "Invalid storage backend: {} (must be one of: {})",
@@ -121,9 +179,41 @@ impl Validator {
                 .into());
             }
 
-            // If backend is file, file config must be set
-            if config.backend == "file" && config.file.is_none() {
-                return Err("file storage config must be set when backend is 'file'".into());
+            // If backend is file or bincode, file config must be set (the
+            // bincode sink reuses `file.sessions_dir`, just with a more
+            // compact on-disk encoding)
+            if (config.backend == "file" || config.backend == "bincode") && config.file.is_none() {
+                return Err(format!(
+                    "file storage config must be set when backend is '{}'",
+                    config.backend
+                )
+                .into());
+            }
+
+            // If backend is postgres, postgres config must be set
+            if config.backend == "postgres" && config.postgres.is_none() {
+                return Err("postgres config must be set when backend is 'postgres'".into());
+            }
+
+            // If backend is broker, broker config must be set
+            if config.backend == "broker" && config.broker.is_none() {
+                return Err("broker config must be set when backend is 'broker'".into());
+            }
+        }
+
+        // Postgres can also be layered on top of another backend (e.g.
+        // file + database); validate its DSN whenever it's configured at all
+        if let Some(postgres) = &config.postgres {
+            if postgres.dsn.is_empty() {
+                return Err("postgres.dsn cannot be empty".into());
+            }
+        }
+
+        // Broker can likewise be layered on top of another backend (e.g.
+        // file + broker); validate its endpoint whenever it's configured
+        if let Some(broker) = &config.broker {
+            if broker.endpoint.is_empty() {
+                return Err("broker.endpoint cannot be empty".into());
             }
         }
 
@@ -141,6 +231,12 @@ impl Validator {
             return Err("max_history must be greater than 0".into());
         }
 
+        // A personality is chosen per-session from this pool; an empty pool
+        // would leave nothing to select from
+        if config.personalities.is_empty() {
+            return Err("personalities cannot be empty".into());
+        }
+
         Ok(())
     }
 
@@ -209,6 +305,24 @@ mod tests {
         assert!(validator.validate(&config).is_err());
     }
 
+    #[test]
+    fn test_validate_syslog_output_requires_known_facility() {
+        let mut config = Config::default();
+        config.logging.output = "syslog".to_string();
+        config.logging.facility = "local9".to_string();
+        let validator = Validator::new();
+        assert!(validator.validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_validate_syslog_output_accepts_known_facility() {
+        let mut config = Config::default();
+        config.logging.output = "syslog".to_string();
+        config.logging.facility = "local3".to_string();
+        let validator = Validator::new();
+        assert!(validator.validate(&config).is_ok());
+    }
+
     #[test]
     fn test_validate_invalid_whitelist_ip() {
         let mut config = Config::default();
@@ -225,6 +339,14 @@ mod tests {
         assert!(validator.validate(&config).is_err());
     }
 
+    #[test]
+    fn test_validate_empty_personality_pool() {
+        let mut config = Config::default();
+        config.shell.personalities = Vec::new();
+        let validator = Validator::new();
+        assert!(validator.validate(&config).is_err());
+    }
+
     #[test]
     fn test_validate_file_size_too_large() {
         let mut config = Config::default();
@@ -232,4 +354,96 @@ mod tests {
         let validator = Validator::new();
         assert!(validator.validate(&config).is_err());
     }
+
+    #[test]
+    fn test_validate_invalid_blocklist_backend() {
+        let mut config = Config::default();
+        config.security.blocklist_backend = "iptables".to_string();
+        let validator = Validator::new();
+        assert!(validator.validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_validate_nft_backend_requires_table_and_set() {
+        let mut config = Config::default();
+        config.security.blocklist_backend = "nft".to_string();
+        config.security.nft_table = String::new();
+        let validator = Validator::new();
+        assert!(validator.validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_validate_ipset_backend_requires_set_name() {
+        let mut config = Config::default();
+        config.security.blocklist_backend = "ipset".to_string();
+        config.security.ipset_name = String::new();
+        let validator = Validator::new();
+        assert!(validator.validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_validate_file_backend_requires_path() {
+        let mut config = Config::default();
+        config.security.blocklist_backend = "file".to_string();
+        config.security.blocklist_file_path = None;
+        let validator = Validator::new();
+        assert!(validator.validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_validate_zero_offender_threshold_rejected() {
+        let mut config = Config::default();
+        config.security.offender_auth_threshold = 0;
+        let validator = Validator::new();
+        assert!(validator.validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_validate_postgres_backend_requires_config() {
+        let mut config = Config::default();
+        config.storage.backend = "postgres".to_string();
+        config.storage.postgres = None;
+        let validator = Validator::new();
+        assert!(validator.validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_validate_postgres_requires_nonempty_dsn() {
+        let mut config = Config::default();
+        config.storage.postgres = Some(PostgresConfig {
+            dsn: String::new(),
+            batch_size: 50,
+            flush_interval_secs: HumanDuration::from_secs(5),
+        });
+        let validator = Validator::new();
+        assert!(validator.validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_validate_bincode_backend_requires_file_config() {
+        let mut config = Config::default();
+        config.storage.backend = "bincode".to_string();
+        config.storage.file = None;
+        let validator = Validator::new();
+        assert!(validator.validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_validate_broker_backend_requires_config() {
+        let mut config = Config::default();
+        config.storage.backend = "broker".to_string();
+        config.storage.broker = None;
+        let validator = Validator::new();
+        assert!(validator.validate(&config).is_err());
+    }
+
+    #[test]
+    fn test_validate_broker_requires_nonempty_endpoint() {
+        let mut config = Config::default();
+        config.storage.broker = Some(BrokerConfig {
+            endpoint: String::new(),
+        });
+        let validator = Validator::new();
+        assert!(validator.validate(&config).is_err());
+    }
 }
@@ -8,11 +8,12 @@
 
 pub mod capture;
 pub mod config;
+pub mod metrics;
 pub mod security;
 pub mod server;
 pub mod shell;
 
-pub use config::Config;
+pub use config::{CliOverrides, Config, ConfigLoader};
 pub use server::SshHoneypot;
 
 /// Result type for DrHPotter operations
@@ -3,15 +3,75 @@
 //! A minimal, secure SSH honeypot implementation in Rust.
 
 use clap::Parser;
-use drhpotter::{Config, SshHoneypot};
+use drhpotter::{CliOverrides, ConfigLoader, SshHoneypot};
 use std::path::PathBuf;
-use tracing::{info, error};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
+use std::sync::{Arc, Mutex};
+use tracing::{info, error, warn};
+use tracing_subscriber::{layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Layer};
+
+/// Adapts a `syslog` connection so it can be shared across `tracing`
+/// writers: each formatted log line is written as one syslog message
+#[derive(Clone)]
+struct SyslogWriter(Arc<Mutex<syslog::Logger<syslog::LoggerBackend, syslog::Formatter3164>>>);
+
+impl std::io::Write for SyslogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let message = String::from_utf8_lossy(buf);
+        self.0
+            .lock()
+            .unwrap()
+            .info(message.trim_end())
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Map a configured facility name to the `syslog` crate's enum, defaulting
+/// to `LOG_DAEMON` for anything `Validator` hasn't already rejected
+fn syslog_facility(name: &str) -> syslog::Facility {
+    match name {
+        "user" => syslog::Facility::LOG_USER,
+        "cron" => syslog::Facility::LOG_CRON,
+        "authpriv" => syslog::Facility::LOG_AUTHPRIV,
+        "local0" => syslog::Facility::LOG_LOCAL0,
+        "local1" => syslog::Facility::LOG_LOCAL1,
+        "local2" => syslog::Facility::LOG_LOCAL2,
+        "local3" => syslog::Facility::LOG_LOCAL3,
+        "local4" => syslog::Facility::LOG_LOCAL4,
+        "local5" => syslog::Facility::LOG_LOCAL5,
+        "local6" => syslog::Facility::LOG_LOCAL6,
+        "local7" => syslog::Facility::LOG_LOCAL7,
+        _ => syslog::Facility::LOG_DAEMON,
+    }
+}
+
+/// Connect to the local syslog daemon over its default Unix socket,
+/// returning `None` (and falling back to stdout) if it isn't reachable
+fn connect_syslog(facility: &str) -> Option<SyslogWriter> {
+    let formatter = syslog::Formatter3164 {
+        facility: syslog_facility(facility),
+        hostname: None,
+        process: "drhpotter".to_string(),
+        pid: std::process::id(),
+    };
+
+    match syslog::unix(formatter) {
+        Ok(logger) => Some(SyslogWriter(Arc::new(Mutex::new(logger)))),
+        Err(e) => {
+            eprintln!("Failed to connect to syslog, falling back to stdout: {}", e);
+            None
+        }
+    }
+}
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Path to configuration file
+    /// Path to configuration file; bypasses the default search paths
     #[arg(short, long, value_name = "FILE")]
     config: Option<PathBuf>,
 
@@ -19,69 +79,145 @@ struct Args {
     #[arg(short, long)]
     port: Option<u16>,
 
+    /// Address to bind to (overrides config)
+    #[arg(long, value_name = "ADDR")]
+    listen_addr: Option<String>,
+
     /// Log level (overrides config)
     #[arg(long, value_name = "LEVEL")]
     log_level: Option<String>,
 
-    /// Show current configuration and exit
+    /// Resolve the fully merged configuration, print it as TOML, and exit
+    #[arg(long)]
+    dump_config: bool,
+
+    /// Write a fully-populated, commented default configuration to the
+    /// first writable search path and exit; refuses to overwrite an
+    /// existing file unless `--force` is also given
+    #[arg(long)]
+    generate_config: bool,
+
+    /// Allow `--generate-config` to overwrite an existing config file
+    #[arg(long)]
+    force: bool,
+
+    /// Validate the fully merged configuration and exit: 0 if valid,
+    /// non-zero otherwise
     #[arg(long)]
-    show_config: bool,
+    validate_config: bool,
+
+    /// Wire up the full server/storage/shell stack and shut down cleanly
+    /// without binding a listener; used by integration tests to exercise
+    /// startup wiring
+    #[arg(long, hide = true)]
+    immediate_shutdown: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let args = Args::parse();
 
-    // Load configuration
-    let mut config = if let Some(config_path) = &args.config {
-        info!("Loading configuration from {:?}", config_path);
-        Config::from_file(config_path.clone())?
-    } else {
-        Config::load()?
+    if args.generate_config {
+        return match ConfigLoader::new().init_default(args.force) {
+            Ok(path) => {
+                println!("Wrote default configuration to {:?}", path);
+                Ok(())
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                Err(e)
+            }
+        };
+    }
+
+    // Load configuration: CLI args > environment > file > Config::default
+    let overrides = CliOverrides {
+        port: args.port,
+        listen_addr: args.listen_addr.clone(),
+        log_level: args.log_level.clone(),
     };
+    let config = ConfigLoader::new().load_with_overrides(args.config.clone(), &overrides)?;
 
-    // Apply CLI overrides
-    if let Some(port) = args.port {
-        config.server.port = port;
-    }
-    if let Some(log_level) = &args.log_level {
-        config.logging.level = log_level.clone();
+    if args.validate_config {
+        println!("Configuration is valid");
+        return Ok(());
     }
 
-    // Initialize logging based on config
+    // Initialize logging based on config. The filter is wrapped in a
+    // `reload::Layer` so a hot-reloaded `logging.level` can be applied to
+    // the running process without a restart.
     let log_level = config.logging.level.clone();
+    let (filter, filter_handle) = reload::Layer::new(
+        EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| format!("drhpotter={}", log_level).into()),
+    );
+    let syslog_writer = (config.logging.output == "syslog")
+        .then(|| connect_syslog(&config.logging.facility))
+        .flatten();
+
+    let log_layer = match (config.logging.format.as_str(), syslog_writer) {
+        ("json", Some(writer)) => tracing_subscriber::fmt::layer().json().with_writer(move || writer.clone()).boxed(),
+        ("json", None) => tracing_subscriber::fmt::layer().json().boxed(),
+        (_, Some(writer)) => tracing_subscriber::fmt::layer().pretty().with_writer(move || writer.clone()).boxed(),
+        (_, None) => tracing_subscriber::fmt::layer().pretty().boxed(),
+    };
+
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| format!("drhpotter={}", log_level).into()),
-        )
-        .with(
-            if config.logging.format == "json" {
-                tracing_subscriber::fmt::layer().json().boxed()
-            } else {
-                tracing_subscriber::fmt::layer().pretty().boxed()
-            }
-        )
+        .with(filter)
+        .with(log_layer)
         .init();
 
     info!("Starting DrHPotter SSH Honeypot v{}", env!("CARGO_PKG_VERSION"));
 
-    // Show config and exit if requested
-    if args.show_config {
-        println!("Current configuration:");
+    if args.dump_config {
         println!("{}", toml::to_string_pretty(&config)?);
         return Ok(());
     }
 
-    // Validate configuration
-    config.validate()?;
-
     let socket_addr = config.socket_addr()?;
     info!("Configuration loaded successfully");
     info!("Binding to {}", socket_addr);
 
     // Create and run honeypot
-    let honeypot = SshHoneypot::new(&socket_addr.to_string()).await?;
+    let honeypot = SshHoneypot::with_security_config(&socket_addr.to_string(), &config.security)
+        .await?
+        .with_storage(&config.storage)
+        .await
+        .with_download_capture(&config.capture, &config.storage)
+        .await
+        .with_filesystem_profile(&config.shell);
+
+    if args.immediate_shutdown {
+        info!("--immediate-shutdown given; startup wiring complete, shutting down");
+        return Ok(());
+    }
+
+    // Hot-reload the config file when one was passed explicitly; the
+    // implicit search path used by `Config::load()` doesn't tell us which
+    // file it resolved, so there's nothing to watch in that case.
+    if let Some(config_path) = &args.config {
+        match ConfigLoader::new().watch(config_path.clone()) {
+            Ok(rx) => {
+                honeypot.subscribe_config(rx.clone());
+                tokio::spawn(async move {
+                    let mut rx = rx;
+                    loop {
+                        let level = rx.borrow().logging.level.clone();
+                        if let Err(e) = filter_handle.reload(format!("drhpotter={}", level)) {
+                            warn!("Failed to apply reloaded log level: {}", e);
+                        }
+
+                        if rx.changed().await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            Err(e) => warn!("Failed to watch {:?} for changes: {}", config_path, e),
+        }
+    } else {
+        info!("No --config path given; config hot-reload is disabled");
+    }
 
     if let Err(e) = honeypot.run().await {
         error!("Honeypot error: {}", e);
@@ -1,8 +1,10 @@
 //! Security and isolation module
 
+mod offenders;
 mod rate_limit;
 
-pub use rate_limit::RateLimiter;
+pub use offenders::{OffenderSnapshot, OffenderTracker};
+pub use rate_limit::{BlocklistBackend, FileBlocklist, InMemoryBlocklist, IpsetBlocklist, NftBlocklist, RateLimiter};
 
 // Future security features:
 // - Resource limits per session
@@ -0,0 +1,252 @@
+//! Per-IP offender tracking across sessions, escalating repeat attackers to
+//! the configured `BlocklistBackend` instead of only logging them passively.
+//!
+//! Unlike `RateLimiter`, which throttles connection bursts from a single IP,
+//! `OffenderTracker` counts auth attempts and shell command activity in a
+//! sliding window and bans an IP outright once either crosses a threshold —
+//! a built-in fail2ban-style response loop.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use tokio::sync::{watch, Mutex};
+use tracing::{info, warn};
+
+use crate::config::Config;
+
+use super::BlocklistBackend;
+
+/// Sliding-window auth/command counters for a single IP
+#[derive(Debug, Clone, Default)]
+struct OffenderRecord {
+    auth_attempts: usize,
+    command_count: usize,
+    window_start: Option<Instant>,
+    flagged: bool,
+}
+
+/// A point-in-time view of one IP's offender counters, returned by
+/// `OffenderTracker::export` for operator-facing reporting
+#[derive(Debug, Clone)]
+pub struct OffenderSnapshot {
+    pub ip: IpAddr,
+    pub auth_attempts: usize,
+    pub command_count: usize,
+    pub flagged: bool,
+}
+
+/// Tracks auth attempts and command activity per source IP across sessions
+/// in a sliding time window, escalating offenders who cross a threshold to
+/// the configured blocklist backend
+pub struct OffenderTracker {
+    records: Mutex<HashMap<IpAddr, OffenderRecord>>,
+    window_secs: AtomicU64,
+    auth_threshold: AtomicUsize,
+    command_threshold: AtomicUsize,
+    ban_ttl_secs: AtomicU64,
+    tarpit_delay_secs: AtomicU64,
+    backend: Arc<dyn BlocklistBackend>,
+}
+
+impl OffenderTracker {
+    /// Create a new offender tracker backed by `backend`
+    pub fn new(
+        window_secs: u64,
+        auth_threshold: usize,
+        command_threshold: usize,
+        ban_ttl_secs: u64,
+        tarpit_delay_secs: u64,
+        backend: Arc<dyn BlocklistBackend>,
+    ) -> Self {
+        Self {
+            records: Mutex::new(HashMap::new()),
+            window_secs: AtomicU64::new(window_secs),
+            auth_threshold: AtomicUsize::new(auth_threshold),
+            command_threshold: AtomicUsize::new(command_threshold),
+            ban_ttl_secs: AtomicU64::new(ban_ttl_secs),
+            tarpit_delay_secs: AtomicU64::new(tarpit_delay_secs),
+            backend,
+        }
+    }
+
+    /// Apply updated thresholds (e.g. from a hot-reloaded config) without
+    /// losing in-flight offender records
+    pub fn update_limits(
+        &self,
+        window_secs: u64,
+        auth_threshold: usize,
+        command_threshold: usize,
+        ban_ttl_secs: u64,
+        tarpit_delay_secs: u64,
+    ) {
+        self.window_secs.store(window_secs, Ordering::Relaxed);
+        self.auth_threshold.store(auth_threshold, Ordering::Relaxed);
+        self.command_threshold.store(command_threshold, Ordering::Relaxed);
+        self.ban_ttl_secs.store(ban_ttl_secs, Ordering::Relaxed);
+        self.tarpit_delay_secs.store(tarpit_delay_secs, Ordering::Relaxed);
+    }
+
+    /// Extra delay to sleep for a flagged-but-not-yet-banned IP before
+    /// proceeding with authentication
+    pub fn tarpit_delay_secs(&self) -> u64 {
+        self.tarpit_delay_secs.load(Ordering::Relaxed)
+    }
+
+    /// Whether `ip` is currently banned by the backend
+    pub async fn is_banned(&self, ip: IpAddr) -> bool {
+        self.backend.is_banned(ip).await
+    }
+
+    /// Record an auth attempt from `ip`, returning `true` if it is now
+    /// flagged (crossed a threshold on this call or a previous one)
+    pub async fn record_auth(&self, ip: IpAddr) -> bool {
+        self.record(ip, true).await
+    }
+
+    /// Record shell command activity from `ip`, returning `true` if it is
+    /// now flagged
+    pub async fn record_command(&self, ip: IpAddr) -> bool {
+        self.record(ip, false).await
+    }
+
+    async fn record(&self, ip: IpAddr, is_auth: bool) -> bool {
+        let window_secs = self.window_secs.load(Ordering::Relaxed);
+        let auth_threshold = self.auth_threshold.load(Ordering::Relaxed);
+        let command_threshold = self.command_threshold.load(Ordering::Relaxed);
+
+        let mut records = self.records.lock().await;
+        let now = Instant::now();
+        let record = records.entry(ip).or_default();
+
+        let expired = record
+            .window_start
+            .map(|start| now.duration_since(start).as_secs() >= window_secs)
+            .unwrap_or(true);
+        if expired {
+            record.auth_attempts = 0;
+            record.command_count = 0;
+            record.window_start = Some(now);
+            record.flagged = false;
+        }
+
+        if is_auth {
+            record.auth_attempts += 1;
+        } else {
+            record.command_count += 1;
+        }
+
+        let crossed = record.auth_attempts >= auth_threshold || record.command_count >= command_threshold;
+        let already_flagged = record.flagged;
+        if crossed {
+            record.flagged = true;
+        }
+
+        if crossed && !already_flagged {
+            let ban_ttl_secs = self.ban_ttl_secs.load(Ordering::Relaxed);
+            drop(records);
+            match self.backend.ban(ip, ban_ttl_secs).await {
+                Ok(()) => info!(ip = %ip, "Offender threshold crossed; banned"),
+                Err(e) => warn!(ip = %ip, error = %e, "Failed to ban offending IP"),
+            }
+        }
+
+        crossed
+    }
+
+    /// Keep thresholds in sync with a hot-reloaded config stream
+    pub fn spawn_config_watcher(self: Arc<Self>, mut rx: watch::Receiver<Config>) {
+        tokio::spawn(async move {
+            while rx.changed().await.is_ok() {
+                let security = rx.borrow().security.clone();
+                self.update_limits(
+                    security.offender_window_secs.as_secs(),
+                    security.offender_auth_threshold,
+                    security.offender_command_threshold,
+                    security.offender_ban_ttl_secs.as_secs(),
+                    security.tarpit_delay_secs.as_secs(),
+                );
+            }
+        });
+    }
+
+    /// Export the current offender table, e.g. for an operator-facing report
+    pub async fn export(&self) -> Vec<OffenderSnapshot> {
+        self.records
+            .lock()
+            .await
+            .iter()
+            .map(|(ip, record)| OffenderSnapshot {
+                ip: *ip,
+                auth_attempts: record.auth_attempts,
+                command_count: record.command_count,
+                flagged: record.flagged,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::InMemoryBlocklist;
+    use std::net::Ipv4Addr;
+
+    fn tracker(auth_threshold: usize, command_threshold: usize) -> OffenderTracker {
+        OffenderTracker::new(60, auth_threshold, command_threshold, 3600, 5, Arc::new(InMemoryBlocklist::new()))
+    }
+
+    #[tokio::test]
+    async fn test_auth_threshold_flags_and_bans() {
+        let tracker = tracker(3, 100);
+        let ip = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 10));
+
+        assert!(!tracker.record_auth(ip).await);
+        assert!(!tracker.record_auth(ip).await);
+        assert!(tracker.record_auth(ip).await);
+
+        assert!(tracker.is_banned(ip).await);
+    }
+
+    #[tokio::test]
+    async fn test_command_threshold_flags_independently_of_auth() {
+        let tracker = tracker(100, 2);
+        let ip = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 20));
+
+        assert!(!tracker.record_command(ip).await);
+        assert!(tracker.record_command(ip).await);
+        assert!(tracker.is_banned(ip).await);
+    }
+
+    #[tokio::test]
+    async fn test_export_reports_counts() {
+        let tracker = tracker(10, 10);
+        let ip = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 30));
+
+        tracker.record_auth(ip).await;
+        tracker.record_command(ip).await;
+        tracker.record_command(ip).await;
+
+        let snapshot = tracker.export().await;
+        let entry = snapshot.iter().find(|s| s.ip == ip).unwrap();
+        assert_eq!(entry.auth_attempts, 1);
+        assert_eq!(entry.command_count, 2);
+        assert!(!entry.flagged);
+    }
+
+    #[tokio::test]
+    async fn test_different_ips_tracked_independently() {
+        let tracker = tracker(2, 100);
+        let offender = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 40));
+        let innocent = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 41));
+
+        tracker.record_auth(offender).await;
+        tracker.record_auth(offender).await;
+        tracker.record_auth(innocent).await;
+
+        assert!(tracker.is_banned(offender).await);
+        assert!(!tracker.is_banned(innocent).await);
+    }
+}
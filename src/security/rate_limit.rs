@@ -1,65 +1,362 @@
-//! Rate limiting for connections
+//! Rate limiting and enforcement for connections
 
-use std::collections::HashMap;
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
 use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{watch, Mutex};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::Result;
 
 /// Connection record for rate limiting
 #[derive(Debug, Clone)]
 struct ConnectionRecord {
     count: usize,
     window_start: Instant,
+    violations: usize,
+}
+
+/// A persisted ban entry, one per line in the ban file (`<ip> <unix_expiry_secs>`)
+struct BanEntry {
+    ip: IpAddr,
+    expires_at_unix: u64,
+}
+
+/// Backend that enforces IP bans, consulted before a connection is allowed through
+#[async_trait]
+pub trait BlocklistBackend: Send + Sync {
+    /// Ban an IP for `ttl_secs` seconds
+    async fn ban(&self, ip: IpAddr, ttl_secs: u64) -> Result<()>;
+
+    /// Check whether an IP is currently banned
+    async fn is_banned(&self, ip: IpAddr) -> bool;
+}
+
+/// In-memory blocklist, consulted directly in `check_and_record`
+pub struct InMemoryBlocklist {
+    banned: Mutex<HashMap<IpAddr, Instant>>,
+}
+
+impl InMemoryBlocklist {
+    /// Create a new, empty in-memory blocklist
+    pub fn new() -> Self {
+        Self {
+            banned: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Default for InMemoryBlocklist {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl BlocklistBackend for InMemoryBlocklist {
+    async fn ban(&self, ip: IpAddr, ttl_secs: u64) -> Result<()> {
+        let expires_at = Instant::now() + Duration::from_secs(ttl_secs);
+        self.banned.lock().await.insert(ip, expires_at);
+        Ok(())
+    }
+
+    async fn is_banned(&self, ip: IpAddr) -> bool {
+        let mut banned = self.banned.lock().await;
+        match banned.get(&ip) {
+            Some(expires_at) if *expires_at > Instant::now() => true,
+            Some(_) => {
+                banned.remove(&ip);
+                false
+            }
+            None => false,
+        }
+    }
+}
+
+/// Firewall-backed blocklist that shells out to `nft` so offending IPs are
+/// dropped by the kernel before they ever reach this process
+pub struct NftBlocklist {
+    table: String,
+    set_name: String,
 }
 
-/// Rate limiter to prevent abuse
+impl NftBlocklist {
+    /// Create a new nftables-backed blocklist targeting `table`/`set_name`
+    /// (e.g. `inet filter` / `blocked`)
+    pub fn new(table: impl Into<String>, set_name: impl Into<String>) -> Self {
+        Self {
+            table: table.into(),
+            set_name: set_name.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl BlocklistBackend for NftBlocklist {
+    async fn ban(&self, ip: IpAddr, ttl_secs: u64) -> Result<()> {
+        let element = format!("{{ {} timeout {}s }}", ip, ttl_secs);
+        let output = tokio::process::Command::new("nft")
+            .args(["add", "element", &self.table, &self.set_name, &element])
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "nft add element failed for {}: {}",
+                ip,
+                String::from_utf8_lossy(&output.stderr)
+            )
+            .into());
+        }
+
+        info!(ip = %ip, ttl_secs, "Banned IP via nftables");
+        Ok(())
+    }
+
+    async fn is_banned(&self, _ip: IpAddr) -> bool {
+        // Enforcement already happens at the kernel level before packets
+        // reach this process, so there is nothing left to check here.
+        false
+    }
+}
+
+/// Firewall-backed blocklist that shells out to `ipset`, for setups that
+/// manage their block set through ipset rather than a native nftables set
+pub struct IpsetBlocklist {
+    set_name: String,
+}
+
+impl IpsetBlocklist {
+    /// Create a new ipset-backed blocklist targeting `set_name`
+    pub fn new(set_name: impl Into<String>) -> Self {
+        Self {
+            set_name: set_name.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl BlocklistBackend for IpsetBlocklist {
+    async fn ban(&self, ip: IpAddr, ttl_secs: u64) -> Result<()> {
+        let output = tokio::process::Command::new("ipset")
+            .args(["add", &self.set_name, &ip.to_string(), "timeout", &ttl_secs.to_string()])
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "ipset add failed for {}: {}",
+                ip,
+                String::from_utf8_lossy(&output.stderr)
+            )
+            .into());
+        }
+
+        info!(ip = %ip, ttl_secs, "Banned IP via ipset");
+        Ok(())
+    }
+
+    async fn is_banned(&self, _ip: IpAddr) -> bool {
+        // Enforcement already happens at the kernel level before packets
+        // reach this process, so there is nothing left to check here.
+        false
+    }
+}
+
+/// Blocklist backend that appends banned IPs, one per line, to a plaintext
+/// file external tools (a reverse proxy's deny list, a separate fail2ban
+/// action) can consume directly, instead of enforcing anything itself
+pub struct FileBlocklist {
+    path: PathBuf,
+    banned: Mutex<HashSet<IpAddr>>,
+}
+
+impl FileBlocklist {
+    /// Create a new file-backed blocklist that appends to `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            banned: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl BlocklistBackend for FileBlocklist {
+    async fn ban(&self, ip: IpAddr, _ttl_secs: u64) -> Result<()> {
+        let mut banned = self.banned.lock().await;
+        if !banned.insert(ip) {
+            return Ok(());
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(format!("{}\n", ip).as_bytes()).await?;
+
+        info!(ip = %ip, path = %self.path.display(), "Appended banned IP to blocklist file");
+        Ok(())
+    }
+
+    async fn is_banned(&self, ip: IpAddr) -> bool {
+        self.banned.lock().await.contains(&ip)
+    }
+}
+
+/// Rate limiter that escalates repeat offenders to a kernel-level block set
 pub struct RateLimiter {
     connections: Arc<Mutex<HashMap<IpAddr, ConnectionRecord>>>,
-    max_connections: usize,
-    window_seconds: u64,
+    max_connections: AtomicUsize,
+    window_seconds: AtomicU64,
+    backend: Arc<dyn BlocklistBackend>,
+    ban_after_violations: AtomicUsize,
+    persist_path: Option<PathBuf>,
 }
 
 impl RateLimiter {
-    /// Create a new rate limiter
+    /// Create a new rate limiter backed by an in-memory blocklist
     ///
     /// # Arguments
     /// * `max_connections` - Maximum connections allowed per IP
     /// * `window_seconds` - Time window in seconds
     pub fn new(max_connections: usize, window_seconds: u64) -> Self {
-        Self {
-            connections: Arc::new(Mutex::new(HashMap::new())),
+        Self::with_backend(
             max_connections,
             window_seconds,
+            Arc::new(InMemoryBlocklist::new()),
+            3,
+            None,
+        )
+    }
+
+    /// Create a new rate limiter with an explicit blocklist backend, ban
+    /// threshold (violations before a ban is issued), and ban persistence file
+    pub fn with_backend(
+        max_connections: usize,
+        window_seconds: u64,
+        backend: Arc<dyn BlocklistBackend>,
+        ban_after_violations: usize,
+        persist_path: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            connections: Arc::new(Mutex::new(HashMap::new())),
+            max_connections: AtomicUsize::new(max_connections),
+            window_seconds: AtomicU64::new(window_seconds),
+            backend,
+            ban_after_violations: AtomicUsize::new(ban_after_violations),
+            persist_path,
         }
     }
 
+    /// Apply updated limits (e.g. from a hot-reloaded config) without
+    /// dropping any in-flight connection state
+    pub fn update_limits(&self, max_connections_per_ip: usize, window_secs: u64, ban_after_violations: usize) {
+        self.max_connections.store(max_connections_per_ip, Ordering::Relaxed);
+        self.window_seconds.store(window_secs, Ordering::Relaxed);
+        self.ban_after_violations.store(ban_after_violations, Ordering::Relaxed);
+        info!(
+            max_connections_per_ip,
+            window_secs, ban_after_violations, "Rate limiter config reloaded"
+        );
+    }
+
+    /// Spawn a task that applies `SecurityConfig` updates live as they arrive
+    /// on a hot-reload channel
+    pub fn spawn_config_watcher(self: Arc<Self>, mut rx: watch::Receiver<Config>) {
+        tokio::spawn(async move {
+            while rx.changed().await.is_ok() {
+                let security = rx.borrow().security.clone();
+                self.update_limits(
+                    security.max_connections_per_ip,
+                    security.rate_limit_window_secs.as_secs(),
+                    security.ban_after_violations,
+                );
+            }
+        });
+    }
+
+    /// Immediately ban a set of IPs (e.g. from `SecurityConfig::blacklist_ips`)
+    /// for the configured window, persisting each ban
+    pub async fn apply_blacklist(&self, ips: &[String]) {
+        for raw_ip in ips {
+            match raw_ip.parse::<IpAddr>() {
+                Ok(ip) => self.ban(ip).await,
+                Err(_) => warn!("Skipping invalid blacklist IP: {}", raw_ip),
+            }
+        }
+    }
+
+    /// Re-apply bans recorded in the persistence file from a previous run
+    pub async fn reapply_persisted_bans(&self) -> Result<()> {
+        let Some(path) = &self.persist_path else {
+            return Ok(());
+        };
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let contents = fs::read_to_string(path).await?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        for entry in contents.lines().filter_map(parse_ban_line) {
+            if entry.expires_at_unix <= now {
+                continue;
+            }
+            let remaining_ttl = entry.expires_at_unix - now;
+            if let Err(e) = self.backend.ban(entry.ip, remaining_ttl).await {
+                warn!("Failed to reapply ban for {}: {}", entry.ip, e);
+            }
+        }
+
+        Ok(())
+    }
+
     /// Check if IP is allowed to connect and record the connection
     pub async fn check_and_record(&self, ip: IpAddr) -> bool {
+        if self.backend.is_banned(ip).await {
+            return false;
+        }
+
+        let window_seconds = self.window_seconds.load(Ordering::Relaxed);
+        let max_connections = self.max_connections.load(Ordering::Relaxed);
+        let ban_after_violations = self.ban_after_violations.load(Ordering::Relaxed);
+
         let mut connections = self.connections.lock().await;
         let now = Instant::now();
 
         // Clean up old entries periodically
         connections.retain(|_, record| {
-            now.duration_since(record.window_start).as_secs() < self.window_seconds
+            now.duration_since(record.window_start).as_secs() < window_seconds
         });
 
-        // Check current IP
-        match connections.get_mut(&ip) {
+        let allowed = match connections.get_mut(&ip) {
             Some(record) => {
                 let elapsed = now.duration_since(record.window_start).as_secs();
 
-                if elapsed >= self.window_seconds {
+                if elapsed >= window_seconds {
                     // Window expired, reset
                     record.count = 1;
                     record.window_start = now;
                     true
-                } else if record.count < self.max_connections {
+                } else if record.count < max_connections {
                     // Still within limits
                     record.count += 1;
                     true
                 } else {
                     // Rate limit exceeded
+                    record.violations += 1;
                     false
                 }
             }
@@ -70,13 +367,61 @@ impl RateLimiter {
                     ConnectionRecord {
                         count: 1,
                         window_start: now,
+                        violations: 0,
                     },
                 );
                 true
             }
+        };
+
+        if !allowed {
+            let violations = connections.get(&ip).map(|r| r.violations).unwrap_or(0);
+            if violations >= ban_after_violations {
+                drop(connections);
+                self.ban(ip).await;
+            }
+        }
+
+        allowed
+    }
+
+    /// Ban an IP via the configured backend and persist the ban
+    async fn ban(&self, ip: IpAddr) {
+        let window_seconds = self.window_seconds.load(Ordering::Relaxed);
+
+        if let Err(e) = self.backend.ban(ip, window_seconds).await {
+            warn!("Failed to ban {}: {}", ip, e);
+            return;
+        }
+
+        if let Err(e) = self.persist_ban(ip, window_seconds).await {
+            warn!("Failed to persist ban for {}: {}", ip, e);
         }
     }
 
+    /// Append a ban record to the persistence file, if configured
+    async fn persist_ban(&self, ip: IpAddr, window_seconds: u64) -> Result<()> {
+        let Some(path) = &self.persist_path else {
+            return Ok(());
+        };
+
+        let expires_at_unix = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            + window_seconds;
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        file.write_all(format!("{} {}\n", ip, expires_at_unix).as_bytes())
+            .await?;
+
+        Ok(())
+    }
+
     /// Get current connection count for an IP
     pub async fn get_count(&self, ip: IpAddr) -> usize {
         let connections = self.connections.lock().await;
@@ -91,6 +436,14 @@ impl RateLimiter {
     }
 }
 
+/// Parse a `<ip> <unix_expiry_secs>` line from the ban persistence file
+fn parse_ban_line(line: &str) -> Option<BanEntry> {
+    let mut parts = line.split_whitespace();
+    let ip = parts.next()?.parse().ok()?;
+    let expires_at_unix = parts.next()?.parse().ok()?;
+    Some(BanEntry { ip, expires_at_unix })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -146,4 +499,56 @@ mod tests {
         limiter.check_and_record(ip).await;
         assert_eq!(limiter.get_count(ip).await, 2);
     }
+
+    #[tokio::test]
+    async fn test_repeat_offender_gets_banned() {
+        let limiter = RateLimiter::with_backend(1, 60, Arc::new(InMemoryBlocklist::new()), 2, None);
+        let ip = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1));
+
+        assert!(limiter.check_and_record(ip).await);
+        assert!(!limiter.check_and_record(ip).await); // 1st violation
+        assert!(!limiter.check_and_record(ip).await); // 2nd violation -> banned
+
+        // Even a fresh window shouldn't help now that the backend has banned it
+        assert!(!limiter.check_and_record(ip).await);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_blocklist_expires() {
+        let backend = InMemoryBlocklist::new();
+        let ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 5));
+
+        backend.ban(ip, 0).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!backend.is_banned(ip).await);
+    }
+
+    #[test]
+    fn test_parse_ban_line() {
+        let entry = parse_ban_line("203.0.113.7 1700000000").unwrap();
+        assert_eq!(entry.ip, IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)));
+        assert_eq!(entry.expires_at_unix, 1700000000);
+    }
+
+    #[tokio::test]
+    async fn test_file_blocklist_appends_and_tracks_banned_ips() {
+        use tempfile::TempDir;
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("blocklist.txt");
+        let backend = FileBlocklist::new(path.clone());
+        let ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 9));
+
+        assert!(!backend.is_banned(ip).await);
+        backend.ban(ip, 60).await.unwrap();
+        assert!(backend.is_banned(ip).await);
+
+        let contents = fs::read_to_string(&path).await.unwrap();
+        assert_eq!(contents, format!("{}\n", ip));
+
+        // Banning again must not duplicate the entry
+        backend.ban(ip, 60).await.unwrap();
+        let contents = fs::read_to_string(&path).await.unwrap();
+        assert_eq!(contents.lines().count(), 1);
+    }
 }
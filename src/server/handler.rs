@@ -8,9 +8,28 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 use tracing::info;
 
-use crate::capture::SessionLogger;
-use crate::shell::FakeShell;
-use super::SessionInfo;
+use crate::capture::{NullSink, SessionLogger, SessionSink};
+use crate::config::{SecurityConfig, SharedShellConfig, ShellConfig};
+use crate::metrics::AttackerMetrics;
+use crate::security::{InMemoryBlocklist, OffenderTracker};
+use crate::shell::{DownloadCaptureConfig, FakeShell, FilesystemImage};
+use super::scp::{self, ScpReply, ScpSink};
+use super::sftp::SftpSession;
+use super::{ClientFingerprint, HandlerState, SessionInfo};
+
+/// Default cap on scp/sftp uploads when no download-capture config was
+/// supplied, so an unconfigured honeypot still can't be made to buffer an
+/// unbounded amount of attacker-supplied data in memory
+const DEFAULT_UPLOAD_MAX_BYTES: usize = 10 * 1024 * 1024;
+
+/// What an open channel is currently being used for: an ordinary
+/// interactive/exec shell, or one of the binary file-transfer protocols
+/// fast-pathed around the fake shell entirely
+enum ChannelMode {
+    Shell,
+    Scp(ScpSink),
+    Sftp(SftpSession),
+}
 
 /// Handler for individual SSH connections
 pub struct Handler {
@@ -18,21 +37,138 @@ pub struct Handler {
     session_info: Arc<Mutex<SessionInfo>>,
     shell: Arc<Mutex<FakeShell>>,
     logger: Arc<SessionLogger>,
+    download_capture: Option<DownloadCaptureConfig>,
+    channel_mode: Mutex<ChannelMode>,
+    offenders: Arc<OffenderTracker>,
 }
 
 impl Handler {
     /// Create a new handler for a client connection
     pub fn new(addr: Option<SocketAddr>) -> Self {
+        let shell_config: SharedShellConfig = Arc::new(tokio::sync::RwLock::new(ShellConfig::default()));
+        let security = SecurityConfig::default();
+        let offenders = Arc::new(OffenderTracker::new(
+            security.offender_window_secs.as_secs(),
+            security.offender_auth_threshold,
+            security.offender_command_threshold,
+            security.offender_ban_ttl_secs.as_secs(),
+            security.tarpit_delay_secs.as_secs(),
+            Arc::new(InMemoryBlocklist::new()),
+        ));
+        Self::with_state(
+            addr,
+            HandlerState {
+                metrics: Arc::new(AttackerMetrics::new()),
+                shell_config,
+                filesystem_image: Arc::new(FilesystemImage::default_image()),
+                sink: Arc::new(NullSink),
+                download_capture: None,
+                casts_dir: None,
+                offenders,
+            },
+        )
+    }
+
+    /// Create a new handler from the state shared across every connection
+    /// the honeypot accepts (attacker analytics, the live shell config, the
+    /// session-log sink, optional download capture, and the offender tracker)
+    pub fn with_state(addr: Option<SocketAddr>, state: HandlerState) -> Self {
         let session_info = Arc::new(Mutex::new(SessionInfo::new(addr)));
-        let logger = Arc::new(SessionLogger::new(addr));
-        let shell = Arc::new(Mutex::new(FakeShell::new()));
+        let logger = Arc::new(match state.casts_dir {
+            Some(casts_dir) => SessionLogger::with_metrics_sink_and_casts_dir(
+                addr,
+                state.metrics,
+                state.sink,
+                casts_dir,
+            ),
+            None => SessionLogger::with_metrics_and_sink(addr, state.metrics, state.sink),
+        });
+        let source_ip = addr.map(|a| a.ip().to_string());
+        let shell = Arc::new(Mutex::new(match &state.download_capture {
+            Some(download_capture) => FakeShell::with_shell_config_and_capture(
+                state.shell_config.clone(),
+                state.filesystem_image.clone(),
+                download_capture.with_logger(logger.clone()),
+                source_ip,
+            ),
+            None => FakeShell::with_shell_config(state.shell_config.clone(), state.filesystem_image.clone(), source_ip),
+        }));
 
         Self {
             addr,
             session_info,
             shell,
             logger,
+            download_capture: state.download_capture,
+            channel_mode: Mutex::new(ChannelMode::Shell),
+            offenders: state.offenders,
+        }
+    }
+
+    /// Record the TCP-level fingerprint captured when the connection was
+    /// accepted, attaching it to the session and logging it as an event
+    pub async fn record_fingerprint(&self, fingerprint: ClientFingerprint) {
+        self.logger
+            .log_event("fingerprint", &fingerprint.to_event_data())
+            .await;
+        self.session_info.lock().await.fingerprint = Some(fingerprint);
+    }
+
+    /// Fill in the client's SSH version banner on the fingerprint recorded
+    /// by `record_fingerprint`, once the version exchange that reveals it
+    /// has actually completed, and re-emit the fingerprint event with it
+    /// included
+    async fn record_client_version(&self, client_version: String) {
+        let event_data = {
+            let mut session = self.session_info.lock().await;
+            match &mut session.fingerprint {
+                Some(fingerprint) => {
+                    fingerprint.set_client_version(client_version);
+                    Some(fingerprint.to_event_data())
+                }
+                None => None,
+            }
+        };
+
+        if let Some(event_data) = event_data {
+            self.logger.log_event("fingerprint", &event_data).await;
+        }
+    }
+
+    /// Handle the `scp -t`/`scp -f` protocol fast-path recognized in
+    /// `exec_request`, switching the channel into binary sink/source mode
+    /// instead of handing the command to the fake shell
+    async fn start_scp(
+        &mut self,
+        direction: scp::ScpDirection,
+        channel: ChannelId,
+        session: &mut Session,
+    ) -> Result<(), russh::Error> {
+        match direction {
+            scp::ScpDirection::To => {
+                let storage = self.download_capture.as_ref().map(|c| c.storage.clone());
+                let max_bytes = self
+                    .download_capture
+                    .as_ref()
+                    .map(|c| c.max_bytes)
+                    .unwrap_or(DEFAULT_UPLOAD_MAX_BYTES);
+                let sink = ScpSink::new(storage, self.logger.clone(), max_bytes);
+                *self.channel_mode.lock().await = ChannelMode::Scp(sink);
+            }
+            scp::ScpDirection::From => {
+                // The honeypot has nothing real to serve; fail the transfer
+                // immediately rather than pretending to have the file.
+                self.logger.log_event("scp_download_refused", "").await;
+                let mut message = vec![0x02u8];
+                message.extend_from_slice(b"scp: No such file or directory\n");
+                session.data(channel, message.into());
+                session.exit_status_request(channel, 1);
+                session.eof(channel);
+                session.close(channel);
+            }
         }
+
+        Ok(())
     }
 }
 
@@ -59,6 +195,19 @@ impl RusshHandler for Handler {
             self.addr, user, password
         );
 
+        let ip = self.addr.map(|a| a.ip());
+
+        // An IP already over the ban threshold gets refused outright rather
+        // than shown the fake shell
+        if let Some(ip) = ip {
+            if self.offenders.is_banned(ip).await {
+                self.logger.log_event("auth_rejected_banned", user).await;
+                return Ok(Auth::Reject {
+                    proceed_with_methods: None,
+                });
+            }
+        }
+
         // Log authentication attempt
         let mut session = self.session_info.lock().await;
         session.username = Some(user.to_string());
@@ -72,6 +221,14 @@ impl RusshHandler for Handler {
         // Simulate realistic delay (prevent fingerprinting)
         tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
 
+        // Track the attempt across sessions and tarpit IPs that just
+        // crossed the offender threshold before letting them in
+        if let Some(ip) = ip {
+            if self.offenders.record_auth(ip).await {
+                tokio::time::sleep(std::time::Duration::from_secs(self.offenders.tarpit_delay_secs())).await;
+            }
+        }
+
         // Always accept (this is a honeypot!)
         info!("Accepting authentication for user: {}", user);
         Ok(Auth::Accept)
@@ -80,9 +237,13 @@ impl RusshHandler for Handler {
     async fn channel_open_session(
         &mut self,
         _channel: Channel<Msg>,
-        _session: &mut Session,
+        session: &mut Session,
     ) -> Result<bool, Self::Error> {
         info!("Channel session opened from {:?}", self.addr);
+
+        let client_version = String::from_utf8_lossy(session.remote_sshid()).to_string();
+        self.record_client_version(client_version).await;
+
         Ok(true)
     }
 
@@ -105,6 +266,9 @@ impl RusshHandler for Handler {
         self.logger
             .log_event("pty_request", &format!("term={}, cols={}, rows={}", term, col_width, row_height))
             .await;
+        self.logger
+            .set_pty_size(col_width as u16, row_height as u16)
+            .await;
 
         Ok(())
     }
@@ -118,10 +282,37 @@ impl RusshHandler for Handler {
 
         self.logger.log_event("shell_request", "").await;
 
-        // Send welcome banner
-        let banner = b"Welcome to Ubuntu 22.04.1 LTS (GNU/Linux 5.15.0-58-generic x86_64)\n\n\
-                      Last login: Sat Nov  9 10:30:15 2025 from 192.168.1.1\n$ ";
-        session.data(channel, banner.to_vec().into());
+        // Send the welcome banner for this session's chosen personality
+        let mut banner = self.shell.lock().await.banner().await;
+        banner.push_str("$ ");
+        self.logger.log_output(banner.as_bytes()).await;
+        session.data(channel, banner.into_bytes().into());
+
+        Ok(())
+    }
+
+    async fn subsystem_request(
+        &mut self,
+        channel: ChannelId,
+        name: &str,
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        info!("Subsystem request from {:?}: {}", self.addr, name);
+        self.logger.log_event("subsystem_request", name).await;
+
+        if name == "sftp" {
+            let storage = self.download_capture.as_ref().map(|c| c.storage.clone());
+            let max_bytes = self
+                .download_capture
+                .as_ref()
+                .map(|c| c.max_bytes)
+                .unwrap_or(DEFAULT_UPLOAD_MAX_BYTES);
+            *self.channel_mode.lock().await =
+                ChannelMode::Sftp(SftpSession::new(storage, self.logger.clone(), max_bytes));
+            session.channel_success(channel);
+        } else {
+            session.channel_failure(channel);
+        }
 
         Ok(())
     }
@@ -135,13 +326,24 @@ impl RusshHandler for Handler {
         let command = String::from_utf8_lossy(data);
         info!("Exec request from {:?}: {}", self.addr, command);
 
+        if let Some(direction) = scp::detect_direction(&command) {
+            self.logger.log_event("scp_request", &command).await;
+            return self.start_scp(direction, channel, session).await;
+        }
+
         // Execute command in fake shell
         let mut shell = self.shell.lock().await;
         let output = shell.execute(&command).await;
+        drop(shell);
+
+        if let Some(ip) = self.addr.map(|a| a.ip()) {
+            self.offenders.record_command(ip).await;
+        }
 
         self.logger
             .log_command(&command, &output)
             .await;
+        self.logger.log_output(output.as_bytes()).await;
 
         // Send output
         session.data(channel, output.as_bytes().to_vec().into());
@@ -158,7 +360,30 @@ impl RusshHandler for Handler {
         data: &[u8],
         session: &mut Session,
     ) -> Result<(), Self::Error> {
+        let mut mode = self.channel_mode.lock().await;
+        match &mut *mode {
+            ChannelMode::Scp(sink) => {
+                let replies = sink.feed(data).await;
+                for reply in replies {
+                    match reply {
+                        ScpReply::Ack => session.data(channel, vec![0u8].into()),
+                    }
+                }
+                return Ok(());
+            }
+            ChannelMode::Sftp(sftp) => {
+                let replies = sftp.feed(data).await;
+                for reply in replies {
+                    session.data(channel, reply.into());
+                }
+                return Ok(());
+            }
+            ChannelMode::Shell => {}
+        }
+        drop(mode);
+
         let input = String::from_utf8_lossy(data);
+        self.logger.log_input(data).await;
 
         // Handle special characters
         if input.contains('\r') || input.contains('\n') {
@@ -169,10 +394,16 @@ impl RusshHandler for Handler {
                 // Execute command
                 let mut shell = self.shell.lock().await;
                 let output = shell.execute(command).await;
+                drop(shell);
+
+                if let Some(ip) = self.addr.map(|a| a.ip()) {
+                    self.offenders.record_command(ip).await;
+                }
 
                 self.logger
                     .log_command(command, &output)
                     .await;
+                self.logger.log_output(output.as_bytes()).await;
 
                 // Send output
                 session.data(channel, output.as_bytes().to_vec().into());
@@ -196,6 +427,7 @@ impl RusshHandler for Handler {
     ) -> Result<(), Self::Error> {
         info!("Channel closed from {:?}", self.addr);
         self.logger.log_event("channel_close", "").await;
+        self.logger.end_session().await;
         Ok(())
     }
 }
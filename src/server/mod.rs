@@ -1,44 +1,234 @@
 //! SSH Server implementation for the honeypot
 
+mod fingerprint;
 mod handler;
+mod scp;
 mod session;
+mod sftp;
 
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::net::TcpListener;
+use tokio::sync::{watch, RwLock};
 use tracing::{info, warn, error};
 
-use crate::security::RateLimiter;
+use crate::capture::{
+    BincodeSink, BrokerSink, CompositeSink, FileStorage, JsonlSink, NullSink, SessionSink,
+};
+use crate::config::{CaptureConfig, Config, SecurityConfig, SharedShellConfig, ShellConfig, StorageConfig};
+use crate::metrics::{AttackerMetrics, MetricsSnapshot};
+use crate::security::{BlocklistBackend, FileBlocklist, InMemoryBlocklist, IpsetBlocklist, NftBlocklist, OffenderTracker, RateLimiter};
+use crate::shell::{DownloadCaptureConfig, FilesystemImage};
 use crate::Result;
 
+pub use fingerprint::ClientFingerprint;
 pub use handler::Handler;
 pub use session::SessionInfo;
 
+/// How often to log attacker analytics while the server is running
+const METRICS_REPORT_INTERVAL_SECS: u64 = 60;
+
+/// Default directory captured download payloads are written to when no
+/// `files_dir` was configured
+const DEFAULT_FILES_DIR: &str = "./data/captured_files";
+
+/// Everything a `Handler` needs that is shared across every connection the
+/// honeypot accepts, bundled so new connection-scoped capabilities don't
+/// keep growing `SshHoneypot`'s and `Handler`'s constructor parameter lists
+#[derive(Clone)]
+pub struct HandlerState {
+    pub metrics: Arc<AttackerMetrics>,
+    pub shell_config: SharedShellConfig,
+    pub filesystem_image: Arc<FilesystemImage>,
+    pub sink: Arc<dyn SessionSink>,
+    pub download_capture: Option<DownloadCaptureConfig>,
+    pub casts_dir: Option<PathBuf>,
+    pub offenders: Arc<OffenderTracker>,
+}
+
+/// Build the blocklist enforcement backend described by `SecurityConfig`,
+/// shared between the connection-rate limiter and the offender tracker so
+/// bans raised by either land in the same place
+fn build_blocklist_backend(security: &SecurityConfig) -> Arc<dyn BlocklistBackend> {
+    match security.blocklist_backend.as_str() {
+        "nft" => Arc::new(NftBlocklist::new(security.nft_table.clone(), security.nft_set.clone())),
+        "ipset" => Arc::new(IpsetBlocklist::new(security.ipset_name.clone())),
+        "file" => match &security.blocklist_file_path {
+            Some(path) => Arc::new(FileBlocklist::new(PathBuf::from(path))),
+            None => Arc::new(InMemoryBlocklist::new()),
+        },
+        _ => Arc::new(InMemoryBlocklist::new()),
+    }
+}
+
 /// Main SSH honeypot server
 pub struct SshHoneypot {
     listen_addr: String,
     server_key: russh_keys::key::KeyPair,
     rate_limiter: Arc<RateLimiter>,
+    state: HandlerState,
 }
 
 impl SshHoneypot {
-    /// Create a new SSH honeypot server
+    /// Create a new SSH honeypot server with a default rate limiter
+    /// (10 connections per IP per minute, in-memory blocklist)
     pub async fn new(listen_addr: &str) -> Result<Self> {
+        let server_key = russh_keys::key::KeyPair::generate_ed25519()
+            .ok_or("Failed to generate server key")?;
+
+        let security = SecurityConfig::default();
+        let offenders = Arc::new(OffenderTracker::new(
+            security.offender_window_secs.as_secs(),
+            security.offender_auth_threshold,
+            security.offender_command_threshold,
+            security.offender_ban_ttl_secs.as_secs(),
+            security.tarpit_delay_secs.as_secs(),
+            Arc::new(InMemoryBlocklist::new()),
+        ));
+
+        Ok(Self {
+            listen_addr: listen_addr.to_string(),
+            server_key,
+            rate_limiter: Arc::new(RateLimiter::new(10, 60)),
+            state: HandlerState {
+                metrics: Arc::new(AttackerMetrics::new()),
+                shell_config: Arc::new(RwLock::new(ShellConfig::default())),
+                filesystem_image: Arc::new(FilesystemImage::default_image()),
+                sink: Arc::new(NullSink),
+                download_capture: None,
+                casts_dir: None,
+                offenders,
+            },
+        })
+    }
+
+    /// Create a new SSH honeypot server, building its rate limiter, offender
+    /// tracker, and enforcement backend from `SecurityConfig`
+    pub async fn with_security_config(listen_addr: &str, security: &SecurityConfig) -> Result<Self> {
         info!("Initializing SSH honeypot");
 
         // Generate ephemeral server key
         let server_key = russh_keys::key::KeyPair::generate_ed25519()
             .ok_or("Failed to generate server key")?;
 
-        // Initialize rate limiter (10 connections per IP per minute)
-        let rate_limiter = Arc::new(RateLimiter::new(10, 60));
+        let blocklist_backend = build_blocklist_backend(security);
+        let rate_limiter = Arc::new(RateLimiter::with_backend(
+            security.max_connections_per_ip,
+            security.rate_limit_window_secs.as_secs(),
+            blocklist_backend.clone(),
+            security.ban_after_violations,
+            security.ban_persist_path.clone().map(std::path::PathBuf::from),
+        ));
+
+        if let Err(e) = rate_limiter.reapply_persisted_bans().await {
+            warn!("Failed to reapply persisted bans: {}", e);
+        }
+        rate_limiter.apply_blacklist(&security.blacklist_ips).await;
+
+        let offenders = Arc::new(OffenderTracker::new(
+            security.offender_window_secs.as_secs(),
+            security.offender_auth_threshold,
+            security.offender_command_threshold,
+            security.offender_ban_ttl_secs.as_secs(),
+            security.tarpit_delay_secs.as_secs(),
+            blocklist_backend,
+        ));
 
         Ok(Self {
             listen_addr: listen_addr.to_string(),
             server_key,
             rate_limiter,
+            state: HandlerState {
+                metrics: Arc::new(AttackerMetrics::new()),
+                shell_config: Arc::new(RwLock::new(ShellConfig::default())),
+                filesystem_image: Arc::new(FilesystemImage::default_image()),
+                sink: Arc::new(NullSink),
+                download_capture: None,
+                casts_dir: None,
+                offenders,
+            },
         })
     }
 
+    /// Attach the session-log sink(s) and asciicast recording directory
+    /// described by `storage`, replacing the default no-op sink. More than
+    /// one sink (e.g. a JSON file and a Postgres database) can be active at
+    /// once; see `build_session_sink`.
+    pub async fn with_storage(mut self, storage: &StorageConfig) -> Self {
+        self.state.sink = build_session_sink(storage).await;
+        self.state.casts_dir = casts_dir(storage);
+        self
+    }
+
+    /// Attach real payload capture to the fake `wget`/`curl` commands,
+    /// storing fetched files under `storage.file.files_dir`. A no-op if
+    /// `capture.capture_downloads` is disabled.
+    pub async fn with_download_capture(mut self, capture: &CaptureConfig, storage: &StorageConfig) -> Self {
+        if !capture.capture_downloads {
+            return self;
+        }
+
+        let files_dir = storage
+            .file
+            .as_ref()
+            .map(|f| f.files_dir.clone())
+            .unwrap_or_else(|| DEFAULT_FILES_DIR.to_string());
+
+        let file_storage = Arc::new(FileStorage::new(PathBuf::from(files_dir)));
+        if let Err(e) = file_storage.init().await {
+            warn!("Failed to initialize download capture storage: {}", e);
+            return self;
+        }
+
+        self.state.download_capture = Some(DownloadCaptureConfig {
+            storage: file_storage,
+            max_bytes: capture.max_file_size_bytes,
+        });
+        self
+    }
+
+    /// Build the shared session filesystem image described by
+    /// `shell.filesystem` (a real directory snapshot or inline manifest),
+    /// replacing the default built-in decoy tree. Built once at startup;
+    /// unlike the shell personality, the image does not track config
+    /// hot-reloads.
+    pub fn with_filesystem_profile(mut self, shell: &ShellConfig) -> Self {
+        self.state.filesystem_image = Arc::new(FilesystemImage::from_profile(&shell.filesystem));
+        self
+    }
+
+    /// Get a point-in-time snapshot of attacker analytics
+    pub async fn metrics_snapshot(&self) -> MetricsSnapshot {
+        self.state.metrics.snapshot().await
+    }
+
+    /// Get a point-in-time snapshot of the per-IP offender table, for
+    /// operator-facing export
+    pub async fn offenders_snapshot(&self) -> Vec<crate::security::OffenderSnapshot> {
+        self.state.offenders.export().await
+    }
+
+    /// Subscribe the honeypot to a hot-reloadable configuration stream,
+    /// keeping the rate limiter, offender tracker, and shell personality in
+    /// sync with the file on disk without requiring a restart
+    pub fn subscribe_config(&self, rx: watch::Receiver<Config>) {
+        self.rate_limiter.clone().spawn_config_watcher(rx.clone());
+        self.state.offenders.clone().spawn_config_watcher(rx.clone());
+
+        let shell_config = self.state.shell_config.clone();
+        tokio::spawn(async move {
+            let mut rx = rx;
+            loop {
+                let new_shell = rx.borrow().shell.clone();
+                *shell_config.write().await = new_shell;
+
+                if rx.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
     /// Run the honeypot server
     pub async fn run(self) -> Result<()> {
         let config = Arc::new(russh::server::Config {
@@ -50,12 +240,19 @@ impl SshHoneypot {
         info!("SSH honeypot listening on {}", self.listen_addr);
 
         let rate_limiter = self.rate_limiter.clone();
+        let state = self.state.clone();
+
+        tokio::spawn(report_metrics_periodically(state.metrics.clone()));
 
         loop {
             match listener.accept().await {
                 Ok((stream, addr)) => {
                     info!("New connection from {}", addr);
 
+                    let fingerprint = ClientFingerprint::capture(&stream);
+
+                    state.metrics.record_ip(&addr.ip().to_string()).await;
+
                     // Check rate limiting
                     if !rate_limiter.check_and_record(addr.ip()).await {
                         warn!("Rate limit exceeded for {}, dropping connection", addr);
@@ -63,7 +260,8 @@ impl SshHoneypot {
                     }
 
                     let config = config.clone();
-                    let handler = Handler::new(Some(addr));
+                    let handler = Handler::with_state(Some(addr), state.clone());
+                    handler.record_fingerprint(fingerprint).await;
 
                     tokio::spawn(async move {
                         if let Err(e) = russh::server::run_stream(
@@ -83,6 +281,98 @@ impl SshHoneypot {
     }
 }
 
+/// Default file a finished session is appended to when `storage.backend`
+/// is `"file"` but no `sessions_dir` was configured
+const DEFAULT_SESSIONS_DIR: &str = "./data/sessions";
+
+/// Build the session-log sink(s) described by `storage`. `storage.backend`
+/// selects the primary sink; `storage.postgres` and `storage.broker` each
+/// layer an additional export on top regardless of the primary backend, so
+/// e.g. file + database + broker can all be active at once.
+async fn build_session_sink(storage: &StorageConfig) -> Arc<dyn SessionSink> {
+    if !storage.enabled {
+        return Arc::new(NullSink);
+    }
+
+    let mut sinks: Vec<Arc<dyn SessionSink>> = Vec::new();
+
+    match storage.backend.as_str() {
+        "file" => {
+            let sessions_dir = storage
+                .file
+                .as_ref()
+                .map(|f| f.sessions_dir.clone())
+                .unwrap_or_else(|| DEFAULT_SESSIONS_DIR.to_string());
+            sinks.push(Arc::new(JsonlSink::new(PathBuf::from(sessions_dir).join("sessions.jsonl"))));
+        }
+        "bincode" => {
+            let sessions_dir = storage
+                .file
+                .as_ref()
+                .map(|f| f.sessions_dir.clone())
+                .unwrap_or_else(|| DEFAULT_SESSIONS_DIR.to_string());
+            sinks.push(Arc::new(BincodeSink::new(PathBuf::from(sessions_dir).join("sessions.bin"))));
+        }
+        "postgres" | "broker" => {
+            // Handled below via `storage.postgres`/`storage.broker`; nothing
+            // extra to do here.
+        }
+        other => {
+            warn!("Storage backend {:?} has no session sink yet; discarding sessions", other);
+        }
+    }
+
+    if let Some(postgres) = &storage.postgres {
+        match crate::capture::PostgresSink::connect(
+            &postgres.dsn,
+            postgres.batch_size,
+            std::time::Duration::from_secs(postgres.flush_interval_secs.as_secs()),
+        )
+        .await
+        {
+            Ok(sink) => sinks.push(Arc::new(sink)),
+            Err(e) => warn!("Failed to connect Postgres session sink: {}", e),
+        }
+    }
+
+    if let Some(broker) = &storage.broker {
+        match BrokerSink::new(broker.endpoint.clone()) {
+            Ok(sink) => sinks.push(Arc::new(sink)),
+            Err(e) => warn!("Failed to construct broker session sink: {}", e),
+        }
+    }
+
+    match sinks.len() {
+        0 => Arc::new(NullSink),
+        1 => sinks.remove(0),
+        _ => Arc::new(CompositeSink::new(sinks)),
+    }
+}
+
+/// Where finished sessions' asciicast recordings should be written, if
+/// persistent storage with a file backend is enabled
+fn casts_dir(storage: &StorageConfig) -> Option<PathBuf> {
+    if !storage.enabled || storage.backend != "file" {
+        return None;
+    }
+    storage.file.as_ref().map(|f| PathBuf::from(&f.casts_dir))
+}
+
+/// Periodically log cardinality estimates for unique attackers
+async fn report_metrics_periodically(metrics: Arc<AttackerMetrics>) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(METRICS_REPORT_INTERVAL_SECS));
+    loop {
+        interval.tick().await;
+        let snapshot = metrics.snapshot().await;
+        info!(
+            unique_ips = snapshot.unique_ips,
+            unique_usernames = snapshot.unique_usernames,
+            unique_passwords = snapshot.unique_passwords,
+            "Attacker analytics report"
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -92,4 +382,152 @@ mod tests {
         let honeypot = SshHoneypot::new("127.0.0.1:0").await;
         assert!(honeypot.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_honeypot_with_security_config() {
+        let security = crate::config::SecurityConfig::default();
+        let honeypot = SshHoneypot::with_security_config("127.0.0.1:0", &security).await;
+        assert!(honeypot.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_offender_ban_is_visible_to_rate_limiter() {
+        let mut security = crate::config::SecurityConfig::default();
+        security.offender_auth_threshold = 1;
+
+        let honeypot = SshHoneypot::with_security_config("127.0.0.1:0", &security)
+            .await
+            .unwrap();
+        let ip: std::net::IpAddr = "203.0.113.7".parse().unwrap();
+
+        // One auth attempt crosses the threshold and bans the IP through the
+        // offender tracker's view of the shared blocklist backend.
+        assert!(honeypot.state.offenders.record_auth(ip).await);
+
+        // The rate limiter must see the same ban, since both were built from
+        // the same `Arc<dyn BlocklistBackend>`.
+        assert!(!honeypot.rate_limiter.check_and_record(ip).await);
+    }
+
+    #[tokio::test]
+    async fn test_build_session_sink_disabled_storage_is_null() {
+        let mut storage = StorageConfig::default();
+        storage.enabled = false;
+
+        // A disabled sink must not touch disk; there's nothing further to
+        // assert on a trait object, so this just exercises the branch.
+        let _sink = build_session_sink(&storage).await;
+    }
+
+    #[tokio::test]
+    async fn test_with_storage_writes_sessions_to_configured_dir() {
+        use tempfile::TempDir;
+
+        let dir = TempDir::new().unwrap();
+        let mut storage = StorageConfig::default();
+        storage.enabled = true;
+        storage.backend = "file".to_string();
+        storage.file = Some(crate::config::FileStorageConfig {
+            base_path: dir.path().to_string_lossy().to_string(),
+            sessions_dir: dir.path().join("sessions").to_string_lossy().to_string(),
+            files_dir: dir.path().join("files").to_string_lossy().to_string(),
+            casts_dir: dir.path().join("casts").to_string_lossy().to_string(),
+        });
+
+        let honeypot = SshHoneypot::new("127.0.0.1:0").await.unwrap().with_storage(&storage).await;
+        honeypot.state.sink.write_session(&crate::capture::SessionLog::new(None)).await.unwrap();
+
+        let written = tokio::fs::read_to_string(dir.path().join("sessions").join("sessions.jsonl"))
+            .await
+            .unwrap();
+        assert_eq!(written.lines().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_build_session_sink_bincode_backend_writes_to_configured_dir() {
+        use tempfile::TempDir;
+
+        let dir = TempDir::new().unwrap();
+        let mut storage = StorageConfig::default();
+        storage.enabled = true;
+        storage.backend = "bincode".to_string();
+        storage.file = Some(crate::config::FileStorageConfig {
+            base_path: dir.path().to_string_lossy().to_string(),
+            sessions_dir: dir.path().join("sessions").to_string_lossy().to_string(),
+            files_dir: dir.path().join("files").to_string_lossy().to_string(),
+            casts_dir: dir.path().join("casts").to_string_lossy().to_string(),
+        });
+
+        let sink = build_session_sink(&storage).await;
+        sink.write_session(&crate::capture::SessionLog::new(None)).await.unwrap();
+
+        assert!(dir.path().join("sessions").join("sessions.bin").exists());
+    }
+
+    #[tokio::test]
+    async fn test_build_session_sink_layers_broker_on_top_of_file_backend() {
+        use tempfile::TempDir;
+
+        let dir = TempDir::new().unwrap();
+        let mut storage = StorageConfig::default();
+        storage.enabled = true;
+        storage.backend = "file".to_string();
+        storage.file = Some(crate::config::FileStorageConfig {
+            base_path: dir.path().to_string_lossy().to_string(),
+            sessions_dir: dir.path().join("sessions").to_string_lossy().to_string(),
+            files_dir: dir.path().join("files").to_string_lossy().to_string(),
+            casts_dir: dir.path().join("casts").to_string_lossy().to_string(),
+        });
+        // An unreachable endpoint is enough to prove the broker sink was
+        // actually constructed and layered alongside the file sink; a
+        // `CompositeSink` tolerates a failing member without erroring.
+        storage.broker = Some(crate::config::BrokerConfig {
+            endpoint: "http://127.0.0.1:1/sessions".to_string(),
+        });
+
+        let sink = build_session_sink(&storage).await;
+        sink.write_session(&crate::capture::SessionLog::new(None)).await.unwrap();
+
+        let written = tokio::fs::read_to_string(dir.path().join("sessions").join("sessions.jsonl"))
+            .await
+            .unwrap();
+        assert_eq!(written.lines().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_with_download_capture_disabled_leaves_state_empty() {
+        let mut capture = CaptureConfig::default();
+        capture.capture_downloads = false;
+
+        let honeypot = SshHoneypot::new("127.0.0.1:0")
+            .await
+            .unwrap()
+            .with_download_capture(&capture, &StorageConfig::default())
+            .await;
+        assert!(honeypot.state.download_capture.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_with_download_capture_enabled_initializes_storage_dir() {
+        use tempfile::TempDir;
+
+        let dir = TempDir::new().unwrap();
+        let capture = CaptureConfig::default();
+        let mut storage = StorageConfig::default();
+        storage.file = Some(crate::config::FileStorageConfig {
+            base_path: dir.path().to_string_lossy().to_string(),
+            sessions_dir: dir.path().join("sessions").to_string_lossy().to_string(),
+            files_dir: dir.path().join("files").to_string_lossy().to_string(),
+            casts_dir: dir.path().join("casts").to_string_lossy().to_string(),
+        });
+
+        let honeypot = SshHoneypot::new("127.0.0.1:0")
+            .await
+            .unwrap()
+            .with_download_capture(&capture, &storage)
+            .await;
+
+        assert!(honeypot.state.download_capture.is_some());
+        assert!(dir.path().join("files").exists());
+    }
 }
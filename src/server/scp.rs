@@ -0,0 +1,311 @@
+//! Server-side implementation of the `scp` "sink" protocol (`scp -t`), used
+//! to capture files an attacker pushes to the honeypot over an `exec`
+//! channel instead of only through interactive shell commands.
+//!
+//! The wire protocol is a simple line-oriented handshake: the source sends a
+//! `C<mode> <size> <name>\n` control line, the sink acks it with a single
+//! `\0` byte, the source streams exactly `size` raw bytes followed by its
+//! own status byte, and the sink acks once more to signal the write
+//! completed. `T` (timestamp) and `D`/`E` (directory enter/leave) control
+//! lines are acked but otherwise ignored; only file transfers are captured.
+
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::capture::{FileStorage, SessionLogger};
+
+/// Which direction a `scp` invocation over an `exec` channel wants to move
+/// data, inferred from its `-t` (sink, pushing to us)/`-f` (source, pulling
+/// from us) flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScpDirection {
+    /// Attacker is pushing a file to the honeypot (`scp -t`)
+    To,
+    /// Attacker is pulling a file from the honeypot (`scp -f`)
+    From,
+}
+
+/// Recognize the `scp -t`/`scp -f` protocol fast-path inside an `exec_request`
+/// command string, as opposed to an ordinary shell command
+pub fn detect_direction(command: &str) -> Option<ScpDirection> {
+    let mut parts = command.split_whitespace();
+    if parts.next()? != "scp" {
+        return None;
+    }
+
+    let mut to = false;
+    let mut from = false;
+    for part in parts {
+        match part {
+            "-t" => to = true,
+            "-f" => from = true,
+            _ => {}
+        }
+    }
+
+    if to {
+        Some(ScpDirection::To)
+    } else if from {
+        Some(ScpDirection::From)
+    } else {
+        None
+    }
+}
+
+/// A single reply the sink must send back to the source while driving the
+/// handshake forward
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScpReply {
+    /// A bare `\0` acknowledgement
+    Ack,
+}
+
+/// A parsed `C<mode> <size> <name>` control header
+struct FileHeader {
+    name: String,
+    size: usize,
+}
+
+enum Step {
+    /// Waiting for a `C`/`D`/`T`/`E` control line, terminated by `\n`
+    AwaitingControl,
+    /// Reading the raw bytes of a file announced by a `C` header whose
+    /// declared size is within `max_bytes`
+    ReadingFile { header: FileHeader, buf: Vec<u8> },
+    /// The declared size in a `C` header exceeds `max_bytes`; draining and
+    /// discarding `remaining` bytes as they arrive instead of buffering
+    /// them, so an attacker can't force an unbounded in-memory buffer just
+    /// by lying about the size in the control line
+    SkippingOversizedFile { header: FileHeader, remaining: usize },
+    /// Waiting for the single status byte the source sends after file
+    /// data; `buf` is `None` if the file was too large to have been
+    /// buffered in the first place
+    AwaitingDataTrailer { header: FileHeader, buf: Option<Vec<u8>> },
+}
+
+/// Drives the `scp -t` sink handshake for a single `exec` channel, capturing
+/// every uploaded file's name, size, and SHA-256 to `storage` (when
+/// configured; the handshake still completes without it, so the attacker
+/// sees a normal-looking transfer either way)
+pub struct ScpSink {
+    storage: Option<Arc<FileStorage>>,
+    logger: Arc<SessionLogger>,
+    max_bytes: usize,
+    step: Step,
+    line_buf: Vec<u8>,
+}
+
+impl ScpSink {
+    pub fn new(storage: Option<Arc<FileStorage>>, logger: Arc<SessionLogger>, max_bytes: usize) -> Self {
+        Self {
+            storage,
+            logger,
+            max_bytes,
+            step: Step::AwaitingControl,
+            line_buf: Vec::new(),
+        }
+    }
+
+    /// Feed newly-received bytes into the handshake, driving it forward as
+    /// far as the currently-buffered data allows
+    pub async fn feed(&mut self, mut data: &[u8]) -> Vec<ScpReply> {
+        let mut replies = Vec::new();
+
+        while !data.is_empty() {
+            let step = std::mem::replace(&mut self.step, Step::AwaitingControl);
+            match step {
+                Step::AwaitingControl => {
+                    if let Some(pos) = data.iter().position(|&b| b == b'\n') {
+                        self.line_buf.extend_from_slice(&data[..pos]);
+                        data = &data[pos + 1..];
+                        let line = std::mem::take(&mut self.line_buf);
+                        replies.extend(self.handle_control_line(&line));
+                    } else {
+                        self.line_buf.extend_from_slice(data);
+                        data = &[];
+                    }
+                }
+                Step::ReadingFile { header, mut buf } => {
+                    let remaining = header.size - buf.len();
+                    let take = remaining.min(data.len());
+                    buf.extend_from_slice(&data[..take]);
+                    data = &data[take..];
+
+                    if buf.len() >= header.size {
+                        self.step = Step::AwaitingDataTrailer { header, buf: Some(buf) };
+                    } else {
+                        self.step = Step::ReadingFile { header, buf };
+                    }
+                }
+                Step::SkippingOversizedFile { header, remaining } => {
+                    let take = remaining.min(data.len());
+                    data = &data[take..];
+                    let remaining = remaining - take;
+
+                    if remaining == 0 {
+                        self.step = Step::AwaitingDataTrailer { header, buf: None };
+                    } else {
+                        self.step = Step::SkippingOversizedFile { header, remaining };
+                    }
+                }
+                Step::AwaitingDataTrailer { header, buf } => {
+                    let status = data[0];
+                    data = &data[1..];
+                    if status != 0 {
+                        warn!(name = %header.name, status, "scp source reported a transfer error");
+                    }
+                    replies.extend(self.finish_file(header, buf).await);
+                    self.step = Step::AwaitingControl;
+                }
+            }
+        }
+
+        replies
+    }
+
+    fn handle_control_line(&mut self, line: &[u8]) -> Vec<ScpReply> {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            return Vec::new();
+        }
+
+        let (kind, rest) = line.split_at(1);
+        match kind {
+            "T" | "D" | "E" => vec![ScpReply::Ack],
+            "C" => {
+                match parse_file_header(rest) {
+                    Ok(header) if header.size > self.max_bytes => {
+                        warn!(name = %header.name, size = header.size, max = self.max_bytes, "Refusing oversized scp upload");
+                        self.step = Step::SkippingOversizedFile { remaining: header.size, header };
+                    }
+                    Ok(header) => self.step = Step::ReadingFile { header, buf: Vec::new() },
+                    Err(e) => warn!(line = %line, error = %e, "Malformed scp control line"),
+                }
+                vec![ScpReply::Ack]
+            }
+            _ => {
+                warn!(line = %line, "Unexpected scp control line");
+                vec![ScpReply::Ack]
+            }
+        }
+    }
+
+    async fn finish_file(&mut self, header: FileHeader, buf: Option<Vec<u8>>) -> Vec<ScpReply> {
+        // Already rejected and drained without buffering when the `C`
+        // control line's declared size exceeded `max_bytes`.
+        let Some(buf) = buf else {
+            return vec![ScpReply::Ack];
+        };
+
+        let mut hasher = Sha256::new();
+        hasher.update(&buf);
+        let sha256 = hex::encode(hasher.finalize());
+
+        let path = match &self.storage {
+            Some(storage) => match storage.store_file(&buf).await {
+                Ok(hash) => storage.get_path(&hash).display().to_string(),
+                Err(e) => {
+                    warn!(name = %header.name, error = %e, "Failed to store scp upload");
+                    String::new()
+                }
+            },
+            None => String::new(),
+        };
+
+        self.logger
+            .log_download(&format!("scp://{}", header.name), &sha256, buf.len(), &path)
+            .await;
+
+        vec![ScpReply::Ack]
+    }
+}
+
+fn parse_file_header(rest: &str) -> Result<FileHeader, String> {
+    let rest = rest.trim_start();
+    let mut parts = rest.splitn(3, ' ');
+    let _mode = parts.next().ok_or("missing mode")?;
+    let size: usize = parts
+        .next()
+        .ok_or("missing size")?
+        .parse()
+        .map_err(|_| "invalid size".to_string())?;
+    let name = parts.next().ok_or("missing filename")?.to_string();
+    Ok(FileHeader { name, size })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::AttackerMetrics;
+    use tempfile::TempDir;
+
+    fn sink(storage: Option<Arc<FileStorage>>, max_bytes: usize) -> ScpSink {
+        ScpSink::new(
+            storage,
+            Arc::new(SessionLogger::with_metrics(None, Arc::new(AttackerMetrics::new()))),
+            max_bytes,
+        )
+    }
+
+    #[test]
+    fn test_detect_direction_recognizes_sink_and_source() {
+        assert_eq!(detect_direction("scp -t /root/upload.bin"), Some(ScpDirection::To));
+        assert_eq!(detect_direction("scp -f /root/download.bin"), Some(ScpDirection::From));
+    }
+
+    #[test]
+    fn test_detect_direction_ignores_non_scp_commands() {
+        assert_eq!(detect_direction("cat /etc/passwd"), None);
+        assert_eq!(detect_direction("scp user@host:file ."), None);
+    }
+
+    #[tokio::test]
+    async fn test_sink_captures_single_file() {
+        let dir = TempDir::new().unwrap();
+        let storage = Arc::new(FileStorage::new(dir.path().to_path_buf()));
+        storage.init().await.unwrap();
+
+        let mut sink = sink(Some(storage.clone()), 1024);
+        let mut replies = sink.feed(b"C0644 5 hello.txt\n").await;
+        replies.extend(sink.feed(b"world").await);
+        replies.extend(sink.feed(&[0]).await);
+
+        assert_eq!(replies, vec![ScpReply::Ack, ScpReply::Ack]);
+
+        let hash = {
+            let mut hasher = Sha256::new();
+            hasher.update(b"world");
+            hex::encode(hasher.finalize())
+        };
+        assert!(storage.exists(&hash).await);
+    }
+
+    #[tokio::test]
+    async fn test_sink_refuses_oversized_file() {
+        let dir = TempDir::new().unwrap();
+        let storage = Arc::new(FileStorage::new(dir.path().to_path_buf()));
+        storage.init().await.unwrap();
+
+        let mut sink = sink(Some(storage.clone()), 2);
+        let mut replies = sink.feed(b"C0644 5 hello.txt\n").await;
+        replies.extend(sink.feed(b"world").await);
+        replies.extend(sink.feed(&[0]).await);
+
+        assert_eq!(replies, vec![ScpReply::Ack, ScpReply::Ack]);
+
+        let mut hasher = Sha256::new();
+        hasher.update(b"world");
+        let hash = hex::encode(hasher.finalize());
+        assert!(!storage.exists(&hash).await);
+    }
+
+    #[tokio::test]
+    async fn test_sink_acks_timestamp_line_without_capturing() {
+        let mut sink = sink(None, 1024);
+        let replies = sink.feed(b"T1700000000 0 1700000000 0\n").await;
+        assert_eq!(replies, vec![ScpReply::Ack]);
+    }
+}
@@ -5,6 +5,8 @@ use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use uuid::Uuid;
 
+use super::ClientFingerprint;
+
 /// Information about an SSH session
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionInfo {
@@ -24,6 +26,8 @@ pub struct SessionInfo {
     pub auth_attempts: u32,
     /// Whether authentication succeeded
     pub auth_success: bool,
+    /// TCP-level fingerprint captured when the connection was accepted
+    pub fingerprint: Option<ClientFingerprint>,
 }
 
 impl SessionInfo {
@@ -38,6 +42,7 @@ impl SessionInfo {
             password: None,
             auth_attempts: 0,
             auth_success: false,
+            fingerprint: None,
         }
     }
 
@@ -0,0 +1,420 @@
+//! Minimal SFTP subsystem implementation, just enough to capture files an
+//! attacker uploads via `sftp` instead of only via `scp`/shell.
+//!
+//! Only the handful of operations a typical `sftp put` exercises are
+//! implemented for real (`REALPATH`, `OPEN`, `WRITE`, `CLOSE`); everything
+//! else gets a `STATUS` reply so clients don't hang waiting on us, but isn't
+//! otherwise functional — this honeypot has no real filesystem to serve.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::capture::{FileStorage, SessionLogger};
+
+const SSH_FXP_INIT: u8 = 1;
+const SSH_FXP_VERSION: u8 = 2;
+const SSH_FXP_OPEN: u8 = 3;
+const SSH_FXP_CLOSE: u8 = 4;
+const SSH_FXP_READ: u8 = 5;
+const SSH_FXP_WRITE: u8 = 6;
+const SSH_FXP_LSTAT: u8 = 7;
+const SSH_FXP_FSTAT: u8 = 8;
+const SSH_FXP_OPENDIR: u8 = 11;
+const SSH_FXP_REALPATH: u8 = 16;
+const SSH_FXP_STAT: u8 = 17;
+const SSH_FXP_STATUS: u8 = 101;
+const SSH_FXP_HANDLE: u8 = 102;
+const SSH_FXP_NAME: u8 = 104;
+
+const SSH_FX_OK: u32 = 0;
+const SSH_FX_EOF: u32 = 1;
+const SSH_FX_NO_SUCH_FILE: u32 = 2;
+const SSH_FX_FAILURE: u32 = 4;
+const SSH_FX_OP_UNSUPPORTED: u32 = 8;
+
+const PROTOCOL_VERSION: u32 = 3;
+
+/// A small big-endian cursor over a single SFTP packet's payload
+struct Cursor<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn read_u8(&mut self) -> Option<u8> {
+        let b = *self.buf.get(self.pos)?;
+        self.pos += 1;
+        Some(b)
+    }
+
+    fn read_u32(&mut self) -> Option<u32> {
+        let bytes = self.buf.get(self.pos..self.pos + 4)?;
+        self.pos += 4;
+        Some(u32::from_be_bytes(bytes.try_into().ok()?))
+    }
+
+    fn read_u64(&mut self) -> Option<u64> {
+        let bytes = self.buf.get(self.pos..self.pos + 8)?;
+        self.pos += 8;
+        Some(u64::from_be_bytes(bytes.try_into().ok()?))
+    }
+
+    fn read_string_bytes(&mut self) -> Option<Vec<u8>> {
+        let len = self.read_u32()? as usize;
+        let bytes = self.buf.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        Some(bytes.to_vec())
+    }
+
+    fn read_string(&mut self) -> Option<String> {
+        Some(String::from_utf8_lossy(&self.read_string_bytes()?).to_string())
+    }
+
+    fn skip(&mut self, n: usize) -> Option<()> {
+        if self.pos + n > self.buf.len() {
+            return None;
+        }
+        self.pos += n;
+        Some(())
+    }
+
+    /// Skip an SFTP `ATTRS` structure without needing its values
+    fn skip_attrs(&mut self) -> Option<()> {
+        let flags = self.read_u32()?;
+        if flags & 0x0000_0001 != 0 {
+            self.skip(8)?; // SIZE
+        }
+        if flags & 0x0000_0002 != 0 {
+            self.skip(8)?; // UIDGID
+        }
+        if flags & 0x0000_0004 != 0 {
+            self.skip(4)?; // PERMISSIONS
+        }
+        if flags & 0x0000_0008 != 0 {
+            self.skip(8)?; // ACMODTIME
+        }
+        if flags & 0x8000_0000 != 0 {
+            let count = self.read_u32()?;
+            for _ in 0..count {
+                self.read_string_bytes()?;
+                self.read_string_bytes()?;
+            }
+        }
+        Some(())
+    }
+}
+
+fn frame(msg_type: u8, write_body: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+    let mut body = vec![msg_type];
+    write_body(&mut body);
+    let mut out = Vec::with_capacity(4 + body.len());
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    out.extend_from_slice(&body);
+    out
+}
+
+fn write_string(out: &mut Vec<u8>, s: &[u8]) {
+    out.extend_from_slice(&(s.len() as u32).to_be_bytes());
+    out.extend_from_slice(s);
+}
+
+fn status(id: u32, code: u32, message: &str) -> Vec<u8> {
+    frame(SSH_FXP_STATUS, |out| {
+        out.extend_from_slice(&id.to_be_bytes());
+        out.extend_from_slice(&code.to_be_bytes());
+        write_string(out, message.as_bytes());
+        write_string(out, b"");
+    })
+}
+
+/// An in-progress upload tracked by its SFTP handle
+struct OpenFile {
+    name: String,
+    data: Vec<u8>,
+}
+
+/// Drives a single `sftp` subsystem channel: reassembles length-prefixed
+/// SFTP packets from the raw byte stream and replies to the small subset of
+/// operations needed to capture an uploaded file's name, bytes, and SHA-256
+pub struct SftpSession {
+    storage: Option<Arc<FileStorage>>,
+    logger: Arc<SessionLogger>,
+    max_bytes: usize,
+    inbound: Vec<u8>,
+    handles: HashMap<String, OpenFile>,
+    next_handle: u64,
+}
+
+impl SftpSession {
+    pub fn new(storage: Option<Arc<FileStorage>>, logger: Arc<SessionLogger>, max_bytes: usize) -> Self {
+        Self {
+            storage,
+            logger,
+            max_bytes,
+            inbound: Vec::new(),
+            handles: HashMap::new(),
+            next_handle: 0,
+        }
+    }
+
+    /// Feed newly-received bytes, returning the fully-framed SFTP replies
+    /// (each already prefixed with its length) to send back to the client
+    pub async fn feed(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        self.inbound.extend_from_slice(data);
+        let mut replies = Vec::new();
+
+        loop {
+            if self.inbound.len() < 4 {
+                break;
+            }
+            let len = u32::from_be_bytes(self.inbound[0..4].try_into().unwrap()) as usize;
+            if self.inbound.len() < 4 + len {
+                break;
+            }
+
+            let message = self.inbound[4..4 + len].to_vec();
+            self.inbound.drain(0..4 + len);
+
+            if let Some(reply) = self.handle_message(&message).await {
+                replies.push(reply);
+            }
+        }
+
+        replies
+    }
+
+    async fn handle_message(&mut self, message: &[u8]) -> Option<Vec<u8>> {
+        let mut cur = Cursor::new(message);
+        let msg_type = cur.read_u8()?;
+
+        if msg_type == SSH_FXP_INIT {
+            return Some(frame(SSH_FXP_VERSION, |out| {
+                out.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+            }));
+        }
+
+        let id = cur.read_u32()?;
+
+        match msg_type {
+            SSH_FXP_REALPATH => {
+                let path = cur.read_string()?;
+                Some(reply_realpath(id, &path))
+            }
+            SSH_FXP_OPEN => {
+                let path = cur.read_string()?;
+                let _pflags = cur.read_u32()?;
+                cur.skip_attrs()?;
+                Some(self.reply_open(id, path))
+            }
+            SSH_FXP_WRITE => {
+                let handle = cur.read_string()?;
+                let offset = cur.read_u64()?;
+                let chunk = cur.read_string_bytes()?;
+                Some(self.reply_write(id, &handle, offset, &chunk))
+            }
+            SSH_FXP_CLOSE => {
+                let handle = cur.read_string()?;
+                Some(self.reply_close(id, &handle).await)
+            }
+            SSH_FXP_STAT | SSH_FXP_LSTAT | SSH_FXP_FSTAT => {
+                Some(status(id, SSH_FX_NO_SUCH_FILE, "no such file"))
+            }
+            SSH_FXP_READ => Some(status(id, SSH_FX_EOF, "eof")),
+            SSH_FXP_OPENDIR => Some(status(id, SSH_FX_EOF, "eof")),
+            _ => {
+                warn!(msg_type, "Unsupported sftp request");
+                Some(status(id, SSH_FX_OP_UNSUPPORTED, "operation not supported"))
+            }
+        }
+    }
+
+    fn reply_open(&mut self, id: u32, path: String) -> Vec<u8> {
+        let handle = format!("h{}", self.next_handle);
+        self.next_handle += 1;
+        self.handles.insert(handle.clone(), OpenFile { name: path, data: Vec::new() });
+
+        frame(SSH_FXP_HANDLE, |out| {
+            out.extend_from_slice(&id.to_be_bytes());
+            write_string(out, handle.as_bytes());
+        })
+    }
+
+    fn reply_write(&mut self, id: u32, handle: &str, offset: u64, chunk: &[u8]) -> Vec<u8> {
+        let Some(file) = self.handles.get_mut(handle) else {
+            return status(id, SSH_FX_FAILURE, "unknown handle");
+        };
+
+        let offset = offset as usize;
+        if offset + chunk.len() > self.max_bytes {
+            return status(id, SSH_FX_FAILURE, "file too large");
+        }
+        if file.data.len() < offset + chunk.len() {
+            file.data.resize(offset + chunk.len(), 0);
+        }
+        file.data[offset..offset + chunk.len()].copy_from_slice(chunk);
+
+        status(id, SSH_FX_OK, "ok")
+    }
+
+    async fn reply_close(&mut self, id: u32, handle: &str) -> Vec<u8> {
+        let Some(file) = self.handles.remove(handle) else {
+            return status(id, SSH_FX_FAILURE, "unknown handle");
+        };
+
+        let mut hasher = Sha256::new();
+        hasher.update(&file.data);
+        let sha256 = hex::encode(hasher.finalize());
+
+        let path = match &self.storage {
+            Some(storage) => match storage.store_file(&file.data).await {
+                Ok(hash) => storage.get_path(&hash).display().to_string(),
+                Err(e) => {
+                    warn!(name = %file.name, error = %e, "Failed to store sftp upload");
+                    String::new()
+                }
+            },
+            None => String::new(),
+        };
+
+        self.logger
+            .log_download(&format!("sftp://{}", file.name), &sha256, file.data.len(), &path)
+            .await;
+
+        status(id, SSH_FX_OK, "ok")
+    }
+}
+
+fn reply_realpath(id: u32, path: &str) -> Vec<u8> {
+    let resolved = if path.is_empty() || path == "." {
+        "/root".to_string()
+    } else {
+        path.to_string()
+    };
+
+    frame(SSH_FXP_NAME, |out| {
+        out.extend_from_slice(&id.to_be_bytes());
+        out.extend_from_slice(&1u32.to_be_bytes());
+        write_string(out, resolved.as_bytes());
+        write_string(out, resolved.as_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::AttackerMetrics;
+    use tempfile::TempDir;
+
+    fn session(storage: Option<Arc<FileStorage>>, max_bytes: usize) -> SftpSession {
+        SftpSession::new(
+            storage,
+            Arc::new(SessionLogger::with_metrics(None, Arc::new(AttackerMetrics::new()))),
+            max_bytes,
+        )
+    }
+
+    fn packet(msg_type: u8, body: &[u8]) -> Vec<u8> {
+        let mut out = vec![msg_type];
+        out.extend_from_slice(body);
+        frame_raw(&out)
+    }
+
+    fn frame_raw(body: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(4 + body.len());
+        out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        out.extend_from_slice(body);
+        out
+    }
+
+    #[tokio::test]
+    async fn test_init_replies_with_version() {
+        let mut session = session(None, 1024);
+        let replies = session.feed(&packet(SSH_FXP_INIT, &3u32.to_be_bytes())).await;
+
+        assert_eq!(replies.len(), 1);
+        let mut cur = Cursor::new(&replies[0][4..]);
+        assert_eq!(cur.read_u8(), Some(SSH_FXP_VERSION));
+        assert_eq!(cur.read_u32(), Some(PROTOCOL_VERSION));
+    }
+
+    #[tokio::test]
+    async fn test_realpath_replies_with_name() {
+        let mut session = session(None, 1024);
+        let mut body = 1u32.to_be_bytes().to_vec();
+        body.extend_from_slice(&(1u32.to_be_bytes()));
+        body.extend_from_slice(b".");
+        let replies = session.feed(&packet(SSH_FXP_REALPATH, &body)).await;
+
+        assert_eq!(replies.len(), 1);
+        let mut cur = Cursor::new(&replies[0][4..]);
+        assert_eq!(cur.read_u8(), Some(SSH_FXP_NAME));
+        assert_eq!(cur.read_u32(), Some(1)); // request id
+        assert_eq!(cur.read_u32(), Some(1)); // one name entry
+        assert_eq!(cur.read_string(), Some("/root".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_open_write_close_stores_file() {
+        let dir = TempDir::new().unwrap();
+        let storage = Arc::new(FileStorage::new(dir.path().to_path_buf()));
+        storage.init().await.unwrap();
+
+        let mut session = session(Some(storage.clone()), 1024);
+
+        // OPEN id=1, path="hello.txt", pflags=0, attrs flags=0
+        let mut open_body = 1u32.to_be_bytes().to_vec();
+        write_string(&mut open_body, b"hello.txt");
+        open_body.extend_from_slice(&0u32.to_be_bytes()); // pflags
+        open_body.extend_from_slice(&0u32.to_be_bytes()); // attrs flags
+        let replies = session.feed(&packet(SSH_FXP_OPEN, &open_body)).await;
+        assert_eq!(replies.len(), 1);
+        let mut cur = Cursor::new(&replies[0][4..]);
+        assert_eq!(cur.read_u8(), Some(SSH_FXP_HANDLE));
+        assert_eq!(cur.read_u32(), Some(1));
+        let handle = cur.read_string().unwrap();
+
+        // WRITE id=2, handle, offset=0, data="world"
+        let mut write_body = 2u32.to_be_bytes().to_vec();
+        write_string(&mut write_body, handle.as_bytes());
+        write_body.extend_from_slice(&0u64.to_be_bytes());
+        write_string(&mut write_body, b"world");
+        let replies = session.feed(&packet(SSH_FXP_WRITE, &write_body)).await;
+        assert_eq!(replies.len(), 1);
+
+        // CLOSE id=3, handle
+        let mut close_body = 3u32.to_be_bytes().to_vec();
+        write_string(&mut close_body, handle.as_bytes());
+        let replies = session.feed(&packet(SSH_FXP_CLOSE, &close_body)).await;
+        assert_eq!(replies.len(), 1);
+        let mut cur = Cursor::new(&replies[0][4..]);
+        assert_eq!(cur.read_u8(), Some(SSH_FXP_STATUS));
+        assert_eq!(cur.read_u32(), Some(3));
+        assert_eq!(cur.read_u32(), Some(SSH_FX_OK));
+
+        let mut hasher = Sha256::new();
+        hasher.update(b"world");
+        let hash = hex::encode(hasher.finalize());
+        assert!(storage.exists(&hash).await);
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_request_replies_with_status() {
+        let mut session = session(None, 1024);
+        let body = 7u32.to_be_bytes().to_vec(); // handle for REMOVE, id=7
+        let replies = session.feed(&packet(13, &body)).await; // SSH_FXP_REMOVE
+
+        assert_eq!(replies.len(), 1);
+        let mut cur = Cursor::new(&replies[0][4..]);
+        assert_eq!(cur.read_u8(), Some(SSH_FXP_STATUS));
+        assert_eq!(cur.read_u32(), Some(7));
+        assert_eq!(cur.read_u32(), Some(SSH_FX_OP_UNSUPPORTED));
+    }
+}
@@ -1,44 +1,117 @@
 //! Command implementations for the fake shell
 
 use std::collections::HashMap;
-use std::path::PathBuf;
-
-use super::filesystem::FakeFilesystem;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::config::Personality;
+
+use super::dispatcher::{CommandContext, Dispatcher};
+use super::fakegit::FakeGit;
+use super::fetch::FetchOutcome;
+use super::filesystem::{FakeFilesystem, Metadata, NodeKind};
+use super::DownloadCapture;
+
+/// Build the dispatcher populated with every default builtin and alias;
+/// `FakeShell::new` starts from this and deployments extend it further
+/// with `register_command`/`register_alias`
+pub(crate) fn default_dispatcher() -> Dispatcher {
+    let mut dispatcher = Dispatcher::new();
+
+    dispatcher.register_command("pwd", Arc::new(|ctx, _args| cmd_pwd(ctx.current_dir)));
+    dispatcher.register_command("whoami", Arc::new(|ctx, _args| cmd_whoami(ctx.env_vars)));
+    dispatcher.register_command("id", Arc::new(|_ctx, _args| cmd_id()));
+    dispatcher.register_command("uname", Arc::new(|ctx, args| cmd_uname(args, ctx.personality)));
+    dispatcher.register_command("hostname", Arc::new(|ctx, _args| format!("{}\n", ctx.personality.hostname)));
+    dispatcher.register_command("ls", Arc::new(|ctx, args| cmd_ls(args, ctx.filesystem, ctx.current_dir)));
+    dispatcher.register_command("cd", Arc::new(|ctx, args| cmd_cd(args, ctx.current_dir, ctx.filesystem)));
+    dispatcher.register_command("cat", Arc::new(|ctx, args| cmd_cat(args, ctx.filesystem, ctx.current_dir, ctx.personality, ctx.stdin)));
+    dispatcher.register_command("stat", Arc::new(|ctx, args| cmd_stat(args, ctx.filesystem, ctx.current_dir)));
+    dispatcher.register_command("echo", Arc::new(|_ctx, args| cmd_echo(args)));
+    dispatcher.register_command("env", Arc::new(|ctx, _args| cmd_env(ctx.env_vars)));
+    dispatcher.register_command("ps", Arc::new(|_ctx, _args| cmd_ps()));
+    dispatcher.register_command("ifconfig", Arc::new(|ctx, _args| cmd_ifconfig(ctx.personality)));
+    dispatcher.register_command("ip", Arc::new(|ctx, args| cmd_ip(args, ctx.personality)));
+    dispatcher.register_command("netstat", Arc::new(|_ctx, _args| cmd_netstat()));
+    dispatcher.register_command("chmod", Arc::new(|_ctx, args| cmd_chmod(args)));
+    dispatcher.register_command("chown", Arc::new(|_ctx, args| cmd_chown(args)));
+    dispatcher.register_command("rm", Arc::new(|ctx, args| cmd_rm(args, ctx.filesystem, ctx.current_dir)));
+    dispatcher.register_command("mkdir", Arc::new(|ctx, args| cmd_mkdir(args, ctx.filesystem, ctx.current_dir)));
+    dispatcher.register_command("touch", Arc::new(|ctx, args| cmd_touch(args, ctx.filesystem, ctx.current_dir)));
+    dispatcher.register_command("cp", Arc::new(|_ctx, args| cmd_cp(args)));
+    dispatcher.register_command("mv", Arc::new(|_ctx, args| cmd_mv(args)));
+    dispatcher.register_command("history", Arc::new(|_ctx, _args| cmd_history()));
+    dispatcher.register_command("exit", Arc::new(|_ctx, _args| cmd_exit()));
+
+    let git = Arc::new(FakeGit::new());
+    dispatcher.register_command("git", Arc::new(move |ctx: &mut CommandContext, args| git.run(ctx.filesystem, ctx.current_dir, args)));
+
+    dispatcher.register_alias("logout", "exit", Vec::new());
+    dispatcher.register_alias("ll", "ls", vec!["-l".to_string()]);
+    dispatcher.register_alias("dir", "ls", Vec::new());
+
+    dispatcher
+}
 
-/// Execute a command in the fake shell
+/// Execute a command in the fake shell, feeding it `stdin` (the previous
+/// pipeline stage's output, if any) and returning its output alongside a
+/// best-effort exit status so `&&`/`||` chaining can react to it.
+/// `wget`/`curl` are dispatched directly since they're async (fetching a
+/// real payload via `download_capture`); every other builtin is resolved
+/// through `dispatcher`.
+#[allow(clippy::too_many_arguments)]
 pub async fn execute_command(
     cmd: &str,
     args: &[&str],
+    stdin: Option<&str>,
     filesystem: &mut FakeFilesystem,
     current_dir: &mut PathBuf,
     env_vars: &HashMap<String, String>,
-) -> String {
+    personality: &Personality,
+    download_capture: Option<&DownloadCapture>,
+    dispatcher: &Dispatcher,
+) -> (String, i32) {
+    if cmd == "wget" || cmd == "curl" {
+        let output = match cmd {
+            "wget" => cmd_wget(args, download_capture).await,
+            _ => cmd_curl(args, download_capture).await,
+        };
+        let status = exit_code_for(cmd, &output);
+        return (output, status);
+    }
+
+    let mut ctx = CommandContext { filesystem, current_dir, env_vars, personality, stdin };
+    match dispatcher.dispatch(cmd, args, &mut ctx) {
+        Some(output) => {
+            let status = exit_code_for(cmd, &output);
+            (output, status)
+        }
+        None => (format!("bash: {}: command not found\n", cmd), 127),
+    }
+}
+
+/// Best-effort exit status for a builtin's rendered output. Every builtin
+/// here only produces a human-readable transcript rather than a dedicated
+/// return value, so failures are recognized by their own well-known error
+/// text instead.
+fn exit_code_for(cmd: &str, output: &str) -> i32 {
     match cmd {
-        "pwd" => cmd_pwd(current_dir),
-        "whoami" => cmd_whoami(env_vars),
-        "id" => cmd_id(),
-        "uname" => cmd_uname(args),
-        "ls" => cmd_ls(args, filesystem, current_dir),
-        "cd" => cmd_cd(args, current_dir, filesystem),
-        "cat" => cmd_cat(args, filesystem, current_dir),
-        "echo" => cmd_echo(args, env_vars),
-        "env" => cmd_env(env_vars),
-        "ps" => cmd_ps(),
-        "ifconfig" => cmd_ifconfig(),
-        "ip" => cmd_ip(args),
-        "netstat" => cmd_netstat(),
-        "wget" => cmd_wget(args).await,
-        "curl" => cmd_curl(args).await,
-        "chmod" => cmd_chmod(args),
-        "chown" => cmd_chown(args),
-        "rm" => cmd_rm(args),
-        "mkdir" => cmd_mkdir(args),
-        "touch" => cmd_touch(args),
-        "cp" => cmd_cp(args),
-        "mv" => cmd_mv(args),
-        "history" => cmd_history(),
-        "exit" | "logout" => cmd_exit(),
-        _ => format!("{}: command not found\n", cmd),
+        "cd" if output.starts_with("cd: ") => 1,
+        "cat" if output.starts_with("cat: ") => 1,
+        "stat" if output.starts_with("stat: ") => 1,
+        "rm" if output.starts_with("rm: ") => 1,
+        "mkdir" if output.starts_with("mkdir: ") => 1,
+        "touch" if output.starts_with("touch: ") => 1,
+        "cp" if output.starts_with("cp: ") => 1,
+        "mv" if output.starts_with("mv: ") => 1,
+        "chmod" if output.starts_with("chmod: ") => 1,
+        "chown" if output.starts_with("chown: ") => 1,
+        "wget" if output.contains("wget: ") => 1,
+        "curl" if output.contains("curl:") => 1,
+        "ip" if output.starts_with("Usage: ip") => 1,
+        "git" if output.starts_with("fatal: ") || output.contains("is not a git command") => 1,
+        _ if output.ends_with("command not found\n") => 127,
+        _ => 0,
     }
 }
 
@@ -55,15 +128,18 @@ fn cmd_id() -> String {
     "uid=0(root) gid=0(root) groups=0(root)\n".to_string()
 }
 
-fn cmd_uname(args: &[&str]) -> String {
+fn cmd_uname(args: &[&str], personality: &Personality) -> String {
     if args.contains(&"-a") {
-        "Linux honeypot 5.15.0-58-generic #64-Ubuntu SMP Thu Jan 5 11:43:13 UTC 2023 x86_64 x86_64 x86_64 GNU/Linux\n".to_string()
+        format!(
+            "Linux {} {} {}\n",
+            personality.hostname, personality.kernel_release, personality.kernel_version
+        )
     } else if args.contains(&"-r") {
-        "5.15.0-58-generic\n".to_string()
+        format!("{}\n", personality.kernel_release)
     } else if args.contains(&"-s") {
         "Linux\n".to_string()
     } else if args.contains(&"-n") {
-        "honeypot\n".to_string()
+        format!("{}\n", personality.hostname)
     } else if args.contains(&"-m") {
         "x86_64\n".to_string()
     } else {
@@ -79,20 +155,26 @@ fn cmd_ls(args: &[&str], filesystem: &FakeFilesystem, current_dir: &PathBuf) ->
 
     if long_format {
         let mut output = String::new();
-        for entry in entries {
-            if !show_hidden && entry.starts_with('.') {
+        for (name, metadata) in entries {
+            if !show_hidden && name.starts_with('.') {
                 continue;
             }
             output.push_str(&format!(
-                "drwxr-xr-x 2 root root 4096 Nov  9 10:30 {}\n",
-                entry
+                "{mode} 1 {owner} {group} {size:>5} {mtime} {name}\n",
+                mode = format_mode(&metadata),
+                owner = owner_name(metadata.uid),
+                group = owner_name(metadata.gid),
+                size = metadata.size,
+                mtime = metadata.mtime.format("%b %e %H:%M"),
+                name = name,
             ));
         }
         output
     } else {
         let filtered: Vec<_> = entries
             .into_iter()
-            .filter(|e| show_hidden || !e.starts_with('.'))
+            .map(|(name, _)| name)
+            .filter(|name| show_hidden || !name.starts_with('.'))
             .collect();
         if filtered.is_empty() {
             String::new()
@@ -102,6 +184,43 @@ fn cmd_ls(args: &[&str], filesystem: &FakeFilesystem, current_dir: &PathBuf) ->
     }
 }
 
+/// Render a node's type+permission bits the way `ls -l` does, e.g.
+/// "drwxr-xr-x" or "-rw-r--r--"
+fn format_mode(metadata: &Metadata) -> String {
+    let type_char = match metadata.kind {
+        NodeKind::Dir => 'd',
+        NodeKind::Symlink => 'l',
+        NodeKind::File => '-',
+    };
+    let bits = [
+        (0o400, 'r'), (0o200, 'w'), (0o100, 'x'),
+        (0o040, 'r'), (0o020, 'w'), (0o010, 'x'),
+        (0o004, 'r'), (0o002, 'w'), (0o001, 'x'),
+    ];
+    let perms: String = bits
+        .iter()
+        .map(|(mask, c)| if metadata.mode & mask != 0 { *c } else { '-' })
+        .collect();
+    format!("{type_char}{perms}")
+}
+
+/// Render a uid/gid the way a shell would when it can resolve it against
+/// `/etc/passwd`: "root" for 0, or the bare numeric id otherwise
+fn owner_name(id: u32) -> String {
+    if id == 0 { "root".to_string() } else { id.to_string() }
+}
+
+/// Resolve a (possibly relative) command argument against `current_dir`,
+/// matching the convention `cd`/`cat` already use: absolute paths pass
+/// through as-is
+pub(crate) fn resolve_path(arg: &str, current_dir: &Path) -> PathBuf {
+    if arg.starts_with('/') {
+        PathBuf::from(arg)
+    } else {
+        current_dir.join(arg)
+    }
+}
+
 fn cmd_cd(args: &[&str], current_dir: &mut PathBuf, filesystem: &FakeFilesystem) -> String {
     if args.is_empty() {
         *current_dir = PathBuf::from("/root");
@@ -127,16 +246,25 @@ fn cmd_cd(args: &[&str], current_dir: &mut PathBuf, filesystem: &FakeFilesystem)
     }
 }
 
-fn cmd_cat(args: &[&str], filesystem: &FakeFilesystem, current_dir: &PathBuf) -> String {
+fn cmd_cat(
+    args: &[&str],
+    filesystem: &FakeFilesystem,
+    current_dir: &PathBuf,
+    personality: &Personality,
+    stdin: Option<&str>,
+) -> String {
     if args.is_empty() {
-        return "cat: missing operand\n".to_string();
+        return match stdin {
+            Some(input) => input.to_string(),
+            None => "cat: missing operand\n".to_string(),
+        };
     }
 
-    let path = if args[0].starts_with('/') {
-        PathBuf::from(args[0])
-    } else {
-        current_dir.join(args[0])
-    };
+    let path = resolve_path(args[0], current_dir);
+
+    if path == Path::new("/etc/passwd") {
+        return personality.passwd.clone();
+    }
 
     match filesystem.read_file(&path) {
         Some(content) => format!("{}\n", content),
@@ -144,22 +272,42 @@ fn cmd_cat(args: &[&str], filesystem: &FakeFilesystem, current_dir: &PathBuf) ->
     }
 }
 
-fn cmd_echo(args: &[&str], env_vars: &HashMap<String, String>) -> String {
-    let mut output = String::new();
-    for (i, arg) in args.iter().enumerate() {
-        if i > 0 {
-            output.push(' ');
-        }
-        // Simple variable expansion
-        if arg.starts_with('$') {
-            let var_name = &arg[1..];
-            if let Some(value) = env_vars.get(var_name) {
-                output.push_str(value);
-            }
-        } else {
-            output.push_str(arg);
+fn cmd_stat(args: &[&str], filesystem: &FakeFilesystem, current_dir: &PathBuf) -> String {
+    if args.is_empty() {
+        return "stat: missing operand\n".to_string();
+    }
+
+    let path = resolve_path(args[0], current_dir);
+
+    match filesystem.metadata(&path) {
+        Some(metadata) => {
+            let kind_label = match metadata.kind {
+                NodeKind::Dir => "directory",
+                NodeKind::Symlink => "symbolic link",
+                NodeKind::File => "regular file",
+            };
+            format!(
+                "  File: {path}\n  Size: {size}\t\tBlocks: {blocks}\tIO Block: 4096\t{kind_label}\n\
+                Access: ({mode:o}/{mode_str})  Uid: ({uid}/{owner})   Gid: ({gid}/{group})\n\
+                Modify: {mtime}\n",
+                path = path.display(),
+                size = metadata.size,
+                blocks = metadata.size.div_ceil(512),
+                mode = metadata.mode,
+                mode_str = format_mode(&metadata),
+                uid = metadata.uid,
+                owner = owner_name(metadata.uid),
+                gid = metadata.gid,
+                group = owner_name(metadata.gid),
+                mtime = metadata.mtime.format("%Y-%m-%d %H:%M:%S%.9f %z"),
+            )
         }
+        None => format!("stat: cannot statx '{}': No such file or directory\n", args[0]),
     }
+}
+
+fn cmd_echo(args: &[&str]) -> String {
+    let mut output = args.join(" ");
     output.push('\n');
     output
 }
@@ -180,36 +328,56 @@ fn cmd_ps() -> String {
     .to_string()
 }
 
-fn cmd_ifconfig() -> String {
-    r#"eth0: flags=4163<UP,BROADCAST,RUNNING,MULTICAST>  mtu 1500
-        inet 192.168.1.100  netmask 255.255.255.0  broadcast 192.168.1.255
+fn cmd_ifconfig(personality: &Personality) -> String {
+    format!(
+        r#"{iface}: flags=4163<UP,BROADCAST,RUNNING,MULTICAST>  mtu 1500
+        inet {ip}  netmask 255.255.255.0  broadcast {broadcast}
         inet6 fe80::a00:27ff:fe4e:66a1  prefixlen 64  scopeid 0x20<link>
-        ether 08:00:27:4e:66:a1  txqueuelen 1000  (Ethernet)
+        ether {mac}  txqueuelen 1000  (Ethernet)
         RX packets 1234  bytes 567890 (567.8 KB)
         RX errors 0  dropped 0  overruns 0  frame 0
         TX packets 890  bytes 123456 (123.4 KB)
         TX errors 0  dropped 0 overruns 0  carrier 0  collisions 0
-"#
-    .to_string()
+"#,
+        iface = personality.interface_name,
+        ip = personality.ip_address,
+        broadcast = broadcast_address(&personality.ip_address),
+        mac = personality.mac_address,
+    )
 }
 
-fn cmd_ip(args: &[&str]) -> String {
+fn cmd_ip(args: &[&str], personality: &Personality) -> String {
     if args.contains(&"addr") || args.contains(&"a") {
-        r#"1: lo: <LOOPBACK,UP,LOWER_UP> mtu 65536 qdisc noqueue state UNKNOWN group default qlen 1000
+        format!(
+            r#"1: lo: <LOOPBACK,UP,LOWER_UP> mtu 65536 qdisc noqueue state UNKNOWN group default qlen 1000
     link/loopback 00:00:00:00:00:00 brd 00:00:00:00:00:00
     inet 127.0.0.1/8 scope host lo
        valid_lft forever preferred_lft forever
-2: eth0: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc fq_codel state UP group default qlen 1000
-    link/ether 08:00:27:4e:66:a1 brd ff:ff:ff:ff:ff:ff
-    inet 192.168.1.100/24 brd 192.168.1.255 scope global eth0
+2: {iface}: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc fq_codel state UP group default qlen 1000
+    link/ether {mac} brd ff:ff:ff:ff:ff:ff
+    inet {ip}/24 brd {broadcast} scope global {iface}
        valid_lft forever preferred_lft forever
-"#
-        .to_string()
+"#,
+            iface = personality.interface_name,
+            mac = personality.mac_address,
+            ip = personality.ip_address,
+            broadcast = broadcast_address(&personality.ip_address),
+        )
     } else {
         "Usage: ip [ OPTIONS ] OBJECT { COMMAND | help }\n".to_string()
     }
 }
 
+/// Derive the `/24` broadcast address for a dotted-quad IP (e.g.
+/// `192.168.1.100` -> `192.168.1.255`), used only for the fake `ifconfig`/
+/// `ip addr` output
+fn broadcast_address(ip: &str) -> String {
+    match ip.rsplit_once('.') {
+        Some((prefix, _)) => format!("{prefix}.255"),
+        None => ip.to_string(),
+    }
+}
+
 fn cmd_netstat() -> String {
     r#"Active Internet connections (servers and established)
 Proto Recv-Q Send-Q Local Address           Foreign Address         State
@@ -219,14 +387,60 @@ tcp        0      0 192.168.1.100:22        192.168.1.50:54321      ESTABLISHED
     .to_string()
 }
 
-async fn cmd_wget(args: &[&str]) -> String {
+async fn cmd_wget(args: &[&str], download_capture: Option<&DownloadCapture>) -> String {
     if args.is_empty() {
         return "wget: missing URL\n".to_string();
     }
 
     let url = args[args.len() - 1]; // Last arg is usually the URL
 
-    // TODO: Actually download the file and store it for analysis
+    match download_capture {
+        Some(capture) => match capture.fetch(url).await {
+            FetchOutcome::Stored { sha256, size, .. } => format!(
+                "--2025-11-09 10:30:15--  {url}\n\
+                Connecting to {url}... connected.\n\
+                HTTP request sent, awaiting response... 200 OK\n\
+                Length: {size} ({size} bytes)\n\
+                Saving to: 'index.html'\n\
+                \n\
+                index.html          100%[===================>]  {size}  --.-KB/s    in 0s\n\
+                \n\
+                2025-11-09 10:30:15 (45.2 MB/s) - 'index.html' saved [{size}/{size}]\n\
+                (captured as {sha256})\n",
+                url = url,
+                size = size,
+                sha256 = sha256,
+            ),
+            FetchOutcome::Refused(reason) => {
+                format!("--2025-11-09 10:30:15--  {}\nwget: {}\n", url, reason)
+            }
+            FetchOutcome::Failed(reason) => {
+                format!("--2025-11-09 10:30:15--  {}\nwget: unable to resolve or fetch: {}\n", url, reason)
+            }
+        },
+        None => simulated_wget_output(url),
+    }
+}
+
+async fn cmd_curl(args: &[&str], download_capture: Option<&DownloadCapture>) -> String {
+    if args.is_empty() {
+        return "curl: try 'curl --help' for more information\n".to_string();
+    }
+
+    let url = args[args.len() - 1];
+
+    match download_capture {
+        Some(capture) => match capture.fetch(url).await {
+            FetchOutcome::Stored { body_preview, .. } => body_preview,
+            FetchOutcome::Refused(reason) => format!("curl: (1) {}\n", reason),
+            FetchOutcome::Failed(reason) => format!("curl: (7) {}\n", reason),
+        },
+        None => simulated_curl_output(url),
+    }
+}
+
+/// Canned `wget` transcript used when no `DownloadCapture` is configured
+fn simulated_wget_output(url: &str) -> String {
     format!(
         "--2025-11-09 10:30:15--  {}\n\
         Resolving example.com... 93.184.216.34\n\
@@ -242,14 +456,8 @@ async fn cmd_wget(args: &[&str]) -> String {
     )
 }
 
-async fn cmd_curl(args: &[&str]) -> String {
-    if args.is_empty() {
-        return "curl: try 'curl --help' for more information\n".to_string();
-    }
-
-    let url = args[args.len() - 1];
-
-    // TODO: Actually download and analyze the content
+/// Canned `curl` body used when no `DownloadCapture` is configured
+fn simulated_curl_output(url: &str) -> String {
     format!("<!DOCTYPE html>\n<html>\n<head><title>Example</title></head>\n<body>Downloaded from {}</body>\n</html>\n", url)
 }
 
@@ -269,27 +477,33 @@ fn cmd_chown(args: &[&str]) -> String {
     String::new()
 }
 
-fn cmd_rm(args: &[&str]) -> String {
+fn cmd_rm(args: &[&str], filesystem: &mut FakeFilesystem, current_dir: &PathBuf) -> String {
     if args.is_empty() {
         return "rm: missing operand\n".to_string();
     }
-    // Silently succeed (fake filesystem)
+    for arg in args.iter().filter(|a| !a.starts_with('-')) {
+        filesystem.remove(&resolve_path(arg, current_dir));
+    }
     String::new()
 }
 
-fn cmd_mkdir(args: &[&str]) -> String {
+fn cmd_mkdir(args: &[&str], filesystem: &mut FakeFilesystem, current_dir: &PathBuf) -> String {
     if args.is_empty() {
         return "mkdir: missing operand\n".to_string();
     }
-    // Silently succeed (fake filesystem)
+    for arg in args.iter().filter(|a| !a.starts_with('-')) {
+        filesystem.create_dir(resolve_path(arg, current_dir));
+    }
     String::new()
 }
 
-fn cmd_touch(args: &[&str]) -> String {
+fn cmd_touch(args: &[&str], filesystem: &mut FakeFilesystem, current_dir: &PathBuf) -> String {
     if args.is_empty() {
         return "touch: missing file operand\n".to_string();
     }
-    // Silently succeed (fake filesystem)
+    for arg in args.iter().filter(|a| !a.starts_with('-')) {
+        filesystem.touch(resolve_path(arg, current_dir));
+    }
     String::new()
 }
 
@@ -339,9 +553,92 @@ mod tests {
         assert!(cmd_id().contains("uid=0(root)"));
     }
 
+    fn test_personality() -> Personality {
+        Personality {
+            hostname: "honeypot".to_string(),
+            kernel_release: "5.15.0-58-generic".to_string(),
+            kernel_version: "#64-Ubuntu SMP Thu Jan 5 11:43:13 UTC 2023 x86_64 x86_64 x86_64 GNU/Linux".to_string(),
+            banner: String::new(),
+            interface_name: "eth0".to_string(),
+            mac_address: "08:00:27:4e:66:a1".to_string(),
+            ip_address: "192.168.1.100".to_string(),
+            passwd: "root:x:0:0:root:/root:/bin/bash\n".to_string(),
+        }
+    }
+
     #[test]
     fn test_cmd_uname() {
-        assert!(cmd_uname(&["-a"]).contains("Linux"));
-        assert!(cmd_uname(&["-r"]).contains("5.15"));
+        let personality = test_personality();
+        assert!(cmd_uname(&["-a"], &personality).contains("Linux"));
+        assert!(cmd_uname(&["-r"], &personality).contains("5.15"));
+    }
+
+    #[test]
+    fn test_cmd_uname_reports_configured_hostname() {
+        let mut personality = test_personality();
+        personality.hostname = "custom-host".to_string();
+        assert_eq!(cmd_uname(&["-n"], &personality), "custom-host\n");
+    }
+
+    #[test]
+    fn test_cmd_cat_etc_passwd_draws_from_personality() {
+        let filesystem = FakeFilesystem::new();
+        let personality = test_personality();
+        let output = cmd_cat(&["/etc/passwd"], &filesystem, &PathBuf::from("/root"), &personality, None);
+        assert_eq!(output, personality.passwd);
+    }
+
+    #[test]
+    fn test_cmd_cat_with_no_args_reads_stdin() {
+        let filesystem = FakeFilesystem::new();
+        let personality = test_personality();
+        let output = cmd_cat(&[], &filesystem, &PathBuf::from("/root"), &personality, Some("piped in\n"));
+        assert_eq!(output, "piped in\n");
+    }
+
+    #[test]
+    fn test_exit_code_for_success_and_failure() {
+        assert_eq!(exit_code_for("cd", "cd: nope: No such file or directory\n"), 1);
+        assert_eq!(exit_code_for("cd", ""), 0);
+        assert_eq!(exit_code_for("bogus", "bogus: command not found\n"), 127);
+        assert_eq!(exit_code_for("wget", "wget: missing URL\n"), 1);
+        assert_eq!(exit_code_for("pwd", "/root\n"), 0);
+    }
+
+    #[test]
+    fn test_cmd_ifconfig_reports_personality_interface() {
+        let personality = test_personality();
+        let output = cmd_ifconfig(&personality);
+        assert!(output.contains("eth0"));
+        assert!(output.contains("192.168.1.100"));
+        assert!(output.contains("08:00:27:4e:66:a1"));
+    }
+
+    #[tokio::test]
+    async fn test_cmd_wget_without_capture_returns_simulated_output() {
+        let output = cmd_wget(&["http://example.com"], None).await;
+        assert!(output.contains("Saving to"));
+    }
+
+    #[tokio::test]
+    async fn test_cmd_curl_without_capture_returns_simulated_output() {
+        let output = cmd_curl(&["http://example.com"], None).await;
+        assert!(output.contains("Downloaded from"));
+    }
+
+    #[tokio::test]
+    async fn test_cmd_wget_with_capture_refuses_loopback_target() {
+        use crate::capture::{FileStorage, SessionLogger};
+        use std::sync::Arc;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let capture = DownloadCapture {
+            storage: Arc::new(FileStorage::new(dir.path().to_path_buf())),
+            logger: Arc::new(SessionLogger::new(None)),
+            max_bytes: 1024,
+        };
+
+        let output = cmd_wget(&["http://127.0.0.1:9/secret"], Some(&capture)).await;
+        assert!(output.contains("non-public"));
     }
 }
@@ -0,0 +1,158 @@
+//! Table-driven registry of builtin commands, so a deployment can add
+//! host-specific commands (custom banners, fake services) or rename/alias
+//! existing ones without editing the core command set
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::config::Personality;
+
+use super::filesystem::FakeFilesystem;
+
+/// Everything a builtin's handler may need to read or mutate for one
+/// invocation
+pub struct CommandContext<'a> {
+    /// The session's fake filesystem
+    pub filesystem: &'a mut FakeFilesystem,
+    /// The session's current working directory
+    pub current_dir: &'a mut PathBuf,
+    /// The session's environment variables
+    pub env_vars: &'a HashMap<String, String>,
+    /// The personality (hostname, `uname`, `/etc/passwd`, ...) this session
+    /// presents
+    pub personality: &'a Personality,
+    /// The previous pipeline stage's output, if this command was piped into
+    pub stdin: Option<&'a str>,
+}
+
+/// A builtin's handler: given its (already alias-expanded) arguments and
+/// the session context, renders the command's output
+pub type Handler = Arc<dyn Fn(&mut CommandContext, &[&str]) -> String + Send + Sync>;
+
+/// One registered command: its canonical name and handler
+struct Builtin {
+    name: String,
+    handler: Handler,
+}
+
+/// An alias that resolves to a canonical command, inserting `extra_args`
+/// ahead of whatever the caller typed, e.g. `ll` -> `ls -l`
+struct Alias {
+    name: String,
+    target: String,
+    extra_args: Vec<String>,
+}
+
+/// Registry of builtin commands and their aliases. Dispatch resolves
+/// aliases first, then canonical names, so `register_alias` can point at
+/// either a default builtin or one a deployment registered itself.
+#[derive(Default)]
+pub struct Dispatcher {
+    commands: Vec<Builtin>,
+    aliases: Vec<Alias>,
+}
+
+impl Dispatcher {
+    /// An empty dispatcher with no registered commands or aliases
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `name` as a canonical command, replacing any handler
+    /// already registered under that name
+    pub fn register_command(&mut self, name: impl Into<String>, handler: Handler) {
+        let name = name.into();
+        self.commands.retain(|b| b.name != name);
+        self.commands.push(Builtin { name, handler });
+    }
+
+    /// Register `alias` to resolve to `target`, inserting `extra_args`
+    /// ahead of the caller's own arguments, replacing any alias already
+    /// registered under that name
+    pub fn register_alias(&mut self, alias: impl Into<String>, target: impl Into<String>, extra_args: Vec<String>) {
+        let alias = alias.into();
+        self.aliases.retain(|a| a.name != alias);
+        self.aliases.push(Alias { name: alias, target: target.into(), extra_args });
+    }
+
+    /// Resolve `cmd` through its alias (if any) to a canonical name, run
+    /// its handler, and return the rendered output, or `None` if no
+    /// command is registered under that name
+    pub fn dispatch(&self, cmd: &str, args: &[&str], ctx: &mut CommandContext) -> Option<String> {
+        let (name, prefix): (&str, &[String]) = match self.aliases.iter().find(|a| a.name == cmd) {
+            Some(alias) => (alias.target.as_str(), alias.extra_args.as_slice()),
+            None => (cmd, &[]),
+        };
+
+        let builtin = self.commands.iter().find(|b| b.name == name)?;
+        let full_args: Vec<&str> = prefix.iter().map(String::as_str).chain(args.iter().copied()).collect();
+        Some((builtin.handler)(ctx, &full_args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Personality;
+
+    fn test_ctx<'a>(filesystem: &'a mut FakeFilesystem, current_dir: &'a mut PathBuf, env_vars: &'a HashMap<String, String>, personality: &'a Personality) -> CommandContext<'a> {
+        CommandContext { filesystem, current_dir, env_vars, personality, stdin: None }
+    }
+
+    #[test]
+    fn test_dispatch_runs_registered_command() {
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.register_command("greet", Arc::new(|_ctx, args| format!("hello {}\n", args.join(" "))));
+
+        let mut filesystem = FakeFilesystem::new();
+        let mut current_dir = PathBuf::from("/root");
+        let env_vars = HashMap::new();
+        let personality = Personality::default();
+        let mut ctx = test_ctx(&mut filesystem, &mut current_dir, &env_vars, &personality);
+
+        assert_eq!(dispatcher.dispatch("greet", &["world"], &mut ctx), Some("hello world\n".to_string()));
+    }
+
+    #[test]
+    fn test_dispatch_unknown_command_returns_none() {
+        let dispatcher = Dispatcher::new();
+        let mut filesystem = FakeFilesystem::new();
+        let mut current_dir = PathBuf::from("/root");
+        let env_vars = HashMap::new();
+        let personality = Personality::default();
+        let mut ctx = test_ctx(&mut filesystem, &mut current_dir, &env_vars, &personality);
+
+        assert_eq!(dispatcher.dispatch("bogus", &[], &mut ctx), None);
+    }
+
+    #[test]
+    fn test_alias_resolves_to_target_with_extra_args_prepended() {
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.register_command("ls", Arc::new(|_ctx, args| args.join(",")));
+        dispatcher.register_alias("ll", "ls", vec!["-l".to_string()]);
+
+        let mut filesystem = FakeFilesystem::new();
+        let mut current_dir = PathBuf::from("/root");
+        let env_vars = HashMap::new();
+        let personality = Personality::default();
+        let mut ctx = test_ctx(&mut filesystem, &mut current_dir, &env_vars, &personality);
+
+        assert_eq!(dispatcher.dispatch("ll", &["/tmp"], &mut ctx), Some("-l,/tmp".to_string()));
+    }
+
+    #[test]
+    fn test_register_command_replaces_existing_handler() {
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.register_command("greet", Arc::new(|_ctx, _args| "first\n".to_string()));
+        dispatcher.register_command("greet", Arc::new(|_ctx, _args| "second\n".to_string()));
+
+        let mut filesystem = FakeFilesystem::new();
+        let mut current_dir = PathBuf::from("/root");
+        let env_vars = HashMap::new();
+        let personality = Personality::default();
+        let mut ctx = test_ctx(&mut filesystem, &mut current_dir, &env_vars, &personality);
+
+        assert_eq!(dispatcher.dispatch("greet", &[], &mut ctx), Some("second\n".to_string()));
+    }
+}
@@ -0,0 +1,279 @@
+//! Fake `git` subsystem, so a session poking around with `git status`,
+//! `git log`, `git remote -v`, or `git config` inside a directory that
+//! looks like a checkout gets porcelain-realistic output instead of an
+//! instant "this shell doesn't know git" tell
+//!
+//! A repository is anywhere the fake filesystem has a `.git` directory,
+//! same as real git. Its branch, commit history, and remote are fabricated
+//! once and cached for the rest of the session (mirroring starship's
+//! `Context`, which resolves and caches repo info the first time a prompt
+//! module asks for it); staged/unstaged/untracked file lists are derived
+//! fresh from whatever the session has actually written under that root.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use super::filesystem::FakeFilesystem;
+
+/// A fabricated repository's resolved state, computed once per session and
+/// reused across every subsequent `git` invocation
+struct RepoState {
+    root: PathBuf,
+    branch: String,
+    remote_url: String,
+    log: Vec<(&'static str, &'static str)>,
+    modified: Vec<String>,
+    untracked: Vec<String>,
+}
+
+/// Lazily-resolved fake git subsystem for one session. `state` is computed
+/// at most once, the first time any `git` subcommand is run, and reused
+/// for every invocation after that regardless of `current_dir` changes.
+#[derive(Default)]
+pub struct FakeGit {
+    state: OnceLock<Option<RepoState>>,
+}
+
+impl FakeGit {
+    /// A fake git subsystem with no repo resolved yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// This session's repo state, resolving it from `filesystem` the first
+    /// time it's asked for and reusing the cached result afterward; `None`
+    /// if no ancestor of `current_dir` has a `.git` directory
+    fn state(&self, filesystem: &FakeFilesystem, current_dir: &Path) -> Option<&RepoState> {
+        self.state.get_or_init(|| Self::discover(filesystem, current_dir)).as_ref()
+    }
+
+    /// Find the repo root (if any) and fabricate its branch, remote, and
+    /// commit history, deriving modified/untracked files from whatever
+    /// this session has written under that root so far
+    fn discover(filesystem: &FakeFilesystem, current_dir: &Path) -> Option<RepoState> {
+        let root = find_repo_root(filesystem, current_dir)?;
+
+        let mut modified = Vec::new();
+        let mut untracked = Vec::new();
+        for (path, is_new) in filesystem.overlay_changes_under(&root) {
+            let relative = path.strip_prefix(&root).unwrap_or(&path).display().to_string();
+            if is_new {
+                untracked.push(relative);
+            } else {
+                modified.push(relative);
+            }
+        }
+        modified.sort();
+        untracked.sort();
+
+        Some(RepoState {
+            root,
+            branch: "main".to_string(),
+            remote_url: "git@github.com:example/app.git".to_string(),
+            log: vec![
+                ("a1b2c3d", "Fix memory leak in connection pool"),
+                ("4f5e6d7", "Add retry logic for flaky upstream calls"),
+                ("8c9b0a1", "Initial commit"),
+            ],
+            modified,
+            untracked,
+        })
+    }
+
+    /// Run `git <args>` against this session's filesystem and current
+    /// directory, returning the rendered output the way real git would
+    pub fn run(&self, filesystem: &FakeFilesystem, current_dir: &Path, args: &[&str]) -> String {
+        match args.first().copied() {
+            Some("status") => self.status(filesystem, current_dir),
+            Some("log") => self.log(filesystem, current_dir, args),
+            Some("remote") => self.remote(filesystem, current_dir, args),
+            Some("config") => self.config(filesystem, current_dir, args),
+            Some(other) => format!("git: '{}' is not a git command. See 'git --help'.\n", other),
+            None => "usage: git [--version] [--help] <command> [<args>...]\n".to_string(),
+        }
+    }
+
+    fn status(&self, filesystem: &FakeFilesystem, current_dir: &Path) -> String {
+        let Some(state) = self.state(filesystem, current_dir) else {
+            return NOT_A_REPO.to_string();
+        };
+
+        let mut out = format!("On branch {}\n", state.branch);
+
+        if state.modified.is_empty() && state.untracked.is_empty() {
+            out.push_str("nothing to commit, working tree clean\n");
+            return out;
+        }
+
+        if !state.modified.is_empty() {
+            out.push_str("Changes not staged for commit:\n");
+            out.push_str("  (use \"git add <file>...\" to update what will be committed)\n");
+            out.push_str("  (use \"git restore <file>...\" to discard changes in working directory)\n");
+            for path in &state.modified {
+                out.push_str(&format!("\tmodified:   {}\n", path));
+            }
+            out.push('\n');
+        }
+
+        if !state.untracked.is_empty() {
+            out.push_str("Untracked files:\n");
+            out.push_str("  (use \"git add <file>...\" to include in what will be committed)\n");
+            for path in &state.untracked {
+                out.push_str(&format!("\t{}\n", path));
+            }
+            out.push('\n');
+        }
+
+        out.push_str("no changes added to commit (use \"git add\" and/or \"git commit -a\")\n");
+        out
+    }
+
+    fn log(&self, filesystem: &FakeFilesystem, current_dir: &Path, args: &[&str]) -> String {
+        let Some(state) = self.state(filesystem, current_dir) else {
+            return NOT_A_REPO.to_string();
+        };
+
+        if args.contains(&"--oneline") {
+            return state.log.iter().map(|(hash, subject)| format!("{} {}\n", hash, subject)).collect();
+        }
+
+        let mut out = String::new();
+        for (hash, subject) in &state.log {
+            out.push_str(&format!(
+                "commit {}{}\nAuthor: root <root@localhost>\nDate:   Thu Jan 1 00:00:00 1970 +0000\n\n    {}\n\n",
+                hash,
+                "0".repeat(40 - hash.len()),
+                subject,
+            ));
+        }
+        out
+    }
+
+    fn remote(&self, filesystem: &FakeFilesystem, current_dir: &Path, args: &[&str]) -> String {
+        let Some(state) = self.state(filesystem, current_dir) else {
+            return NOT_A_REPO.to_string();
+        };
+
+        if args.contains(&"-v") {
+            format!("origin\t{} (fetch)\norigin\t{} (push)\n", state.remote_url, state.remote_url)
+        } else {
+            "origin\n".to_string()
+        }
+    }
+
+    fn config(&self, filesystem: &FakeFilesystem, current_dir: &Path, args: &[&str]) -> String {
+        let Some(state) = self.state(filesystem, current_dir) else {
+            return NOT_A_REPO.to_string();
+        };
+
+        match args.get(1).copied() {
+            Some("--list") => format!(
+                "core.repositoryformatversion=0\ncore.filemode=true\ncore.bare=false\nremote.origin.url={}\nremote.origin.fetch=+refs/heads/*:refs/remotes/origin/*\nbranch.{}.remote=origin\nbranch.{}.merge=refs/heads/{}\nuser.name=root\nuser.email=root@localhost\n",
+                state.remote_url, state.branch, state.branch, state.branch
+            ),
+            Some("user.name") => "root\n".to_string(),
+            Some("user.email") => "root@localhost\n".to_string(),
+            Some("remote.origin.url") => format!("{}\n", state.remote_url),
+            Some(_) => String::new(),
+            None => "usage: git config [<options>]\n".to_string(),
+        }
+    }
+}
+
+const NOT_A_REPO: &str = "fatal: not a git repository (or any of the parent directories): .git\n";
+
+/// Walk `start` and its ancestors looking for a `.git` directory in the
+/// fake filesystem, returning the first directory that has one
+fn find_repo_root(filesystem: &FakeFilesystem, start: &Path) -> Option<PathBuf> {
+    let mut dir = start;
+    loop {
+        if filesystem.dir_exists(&dir.join(".git")) {
+            return Some(dir.to_path_buf());
+        }
+        dir = dir.parent()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_outside_any_repo_reports_fatal_error() {
+        let filesystem = FakeFilesystem::new();
+        let git = FakeGit::new();
+
+        let output = git.run(&filesystem, Path::new("/root"), &["status"]);
+        assert_eq!(output, NOT_A_REPO);
+    }
+
+    #[test]
+    fn test_status_in_clean_repo_reports_working_tree_clean() {
+        let mut filesystem = FakeFilesystem::new();
+        filesystem.create_dir(PathBuf::from("/root/app/.git"));
+        let git = FakeGit::new();
+
+        let output = git.run(&filesystem, Path::new("/root/app"), &["status"]);
+        assert!(output.contains("On branch main"));
+        assert!(output.contains("nothing to commit, working tree clean"));
+    }
+
+    #[test]
+    fn test_status_lists_untracked_file_created_this_session() {
+        let mut filesystem = FakeFilesystem::new();
+        filesystem.create_dir(PathBuf::from("/root/app/.git"));
+        filesystem.touch(PathBuf::from("/root/app/notes.txt"));
+        let git = FakeGit::new();
+
+        let output = git.run(&filesystem, Path::new("/root/app"), &["status"]);
+        assert!(output.contains("Untracked files:"));
+        assert!(output.contains("notes.txt"));
+    }
+
+    #[test]
+    fn test_log_oneline_shows_seven_char_hash_per_commit() {
+        let mut filesystem = FakeFilesystem::new();
+        filesystem.create_dir(PathBuf::from("/root/app/.git"));
+        let git = FakeGit::new();
+
+        let output = git.run(&filesystem, Path::new("/root/app"), &["log", "--oneline"]);
+        for line in output.lines() {
+            let hash = line.split(' ').next().unwrap();
+            assert_eq!(hash.len(), 7);
+        }
+    }
+
+    #[test]
+    fn test_remote_v_lists_fetch_and_push_urls() {
+        let mut filesystem = FakeFilesystem::new();
+        filesystem.create_dir(PathBuf::from("/root/app/.git"));
+        let git = FakeGit::new();
+
+        let output = git.run(&filesystem, Path::new("/root/app"), &["remote", "-v"]);
+        assert!(output.contains("(fetch)"));
+        assert!(output.contains("(push)"));
+    }
+
+    #[test]
+    fn test_repo_state_is_cached_after_first_resolution() {
+        let mut filesystem = FakeFilesystem::new();
+        filesystem.create_dir(PathBuf::from("/root/app/.git"));
+        let git = FakeGit::new();
+
+        let first = git.run(&filesystem, Path::new("/root/app"), &["status"]);
+        filesystem.touch(PathBuf::from("/root/app/late.txt"));
+        let second = git.run(&filesystem, Path::new("/root/app"), &["status"]);
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_unknown_subcommand_reports_git_style_error() {
+        let mut filesystem = FakeFilesystem::new();
+        filesystem.create_dir(PathBuf::from("/root/app/.git"));
+        let git = FakeGit::new();
+
+        let output = git.run(&filesystem, Path::new("/root/app"), &["frobnicate"]);
+        assert_eq!(output, "git: 'frobnicate' is not a git command. See 'git --help'.\n");
+    }
+}
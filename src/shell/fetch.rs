@@ -0,0 +1,242 @@
+//! Fetching and storing payloads retrieved by the fake `wget`/`curl` commands
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::net::lookup_host;
+
+use crate::capture::{FileStorage, SessionLogger};
+
+/// How long a fetch is allowed to run before being abandoned
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How much of a stored response to echo back through `curl`
+const BODY_PREVIEW_CHARS: usize = 4096;
+
+/// Long-lived download-capture settings, shared across every connection the
+/// honeypot accepts. Combined with a per-connection `SessionLogger` to build
+/// a [`DownloadCapture`].
+#[derive(Clone)]
+pub struct DownloadCaptureConfig {
+    /// Where captured payloads are written, keyed by SHA256
+    pub storage: Arc<FileStorage>,
+    /// Refuse to store responses larger than this
+    pub max_bytes: usize,
+}
+
+impl DownloadCaptureConfig {
+    /// Attach a per-connection session logger, producing the `DownloadCapture`
+    /// a `Handler`'s `FakeShell` actually fetches through
+    pub fn with_logger(&self, logger: Arc<SessionLogger>) -> DownloadCapture {
+        DownloadCapture {
+            storage: self.storage.clone(),
+            logger,
+            max_bytes: self.max_bytes,
+        }
+    }
+}
+
+/// State shared with `FakeShell` so `wget`/`curl` can fetch and store real
+/// payloads instead of returning canned output
+#[derive(Clone)]
+pub struct DownloadCapture {
+    /// Where captured payloads are written, keyed by SHA256
+    pub storage: Arc<FileStorage>,
+    /// Session logger the download is recorded against
+    pub logger: Arc<SessionLogger>,
+    /// Refuse to store responses larger than this
+    pub max_bytes: usize,
+}
+
+/// Outcome of attempting to fetch and store a URL
+pub enum FetchOutcome {
+    /// The payload was downloaded and stored
+    Stored {
+        sha256: String,
+        size: usize,
+        body_preview: String,
+    },
+    /// The request was refused before any bytes were transferred
+    Refused(String),
+    /// The request failed once underway (DNS, connection, timeout, etc.)
+    Failed(String),
+}
+
+impl DownloadCapture {
+    /// Fetch `url`, enforcing an SSRF denylist and a size cap, and store the
+    /// response body if it comes back clean
+    pub async fn fetch(&self, url: &str) -> FetchOutcome {
+        let parsed = match reqwest::Url::parse(url) {
+            Ok(u) => u,
+            Err(e) => return FetchOutcome::Refused(format!("invalid URL: {}", e)),
+        };
+
+        if parsed.scheme() != "http" && parsed.scheme() != "https" {
+            return FetchOutcome::Refused(format!("unsupported scheme: {}", parsed.scheme()));
+        }
+
+        let Some(host) = parsed.host_str() else {
+            return FetchOutcome::Refused("missing host".to_string());
+        };
+        let port = parsed.port_or_known_default().unwrap_or(80);
+
+        // Resolve once and pin the connection to exactly the address that
+        // was validated: letting `reqwest` re-resolve `host` on its own
+        // would open a DNS-rebinding window where the check above and the
+        // actual connection below could land on different addresses.
+        let addr = match resolve_validated(host, port).await {
+            Ok(addr) => addr,
+            Err(e) => return FetchOutcome::Refused(e),
+        };
+
+        let client = match reqwest::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .resolve(host, addr)
+            .build()
+        {
+            Ok(c) => c,
+            Err(e) => return FetchOutcome::Failed(e.to_string()),
+        };
+
+        let mut response = match client.get(parsed).send().await {
+            Ok(r) => r,
+            Err(e) => return FetchOutcome::Failed(e.to_string()),
+        };
+
+        // Enforce `max_bytes` as chunks arrive rather than buffering the
+        // whole body first, so an oversized response is abandoned instead
+        // of fully downloaded into memory before the cap is even checked.
+        let mut body: Vec<u8> = Vec::new();
+        loop {
+            let chunk = match response.chunk().await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
+                Err(e) => return FetchOutcome::Failed(e.to_string()),
+            };
+
+            if body.len() + chunk.len() > self.max_bytes {
+                return FetchOutcome::Refused(format!(
+                    "response too large (exceeds limit of {} bytes)",
+                    self.max_bytes
+                ));
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        match self.storage.store_file(&body).await {
+            Ok(sha256) => {
+                let size = body.len();
+                let path = self.storage.get_path(&sha256);
+                self.logger
+                    .log_download(url, &sha256, size, &path.display().to_string())
+                    .await;
+
+                let body_preview: String = String::from_utf8_lossy(&body)
+                    .chars()
+                    .take(BODY_PREVIEW_CHARS)
+                    .collect();
+
+                FetchOutcome::Stored {
+                    sha256,
+                    size,
+                    body_preview,
+                }
+            }
+            Err(e) => FetchOutcome::Failed(e.to_string()),
+        }
+    }
+}
+
+/// Resolve `host` once, reject it if any address it resolves to is
+/// loopback, private, link-local, or multicast, and return the first
+/// resolved address so the caller can pin its connection to exactly what
+/// was checked here instead of resolving `host` a second time
+async fn resolve_validated(host: &str, port: u16) -> Result<SocketAddr, String> {
+    let addrs: Vec<SocketAddr> = lookup_host((host, port))
+        .await
+        .map_err(|e| format!("DNS resolution failed: {}", e))?
+        .collect();
+
+    if addrs.is_empty() {
+        return Err("host did not resolve to any address".to_string());
+    }
+
+    for addr in &addrs {
+        if is_denylisted(addr.ip()) {
+            return Err(format!(
+                "refusing to fetch from non-public address {}",
+                addr.ip()
+            ));
+        }
+    }
+
+    Ok(addrs[0])
+}
+
+fn is_denylisted(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_multicast()
+                || v4.is_unspecified()
+        }
+        IpAddr::V6(v6) => {
+            v6.is_loopback()
+                || v6.is_multicast()
+                || v6.is_unspecified()
+                || (v6.segments()[0] & 0xfe00) == 0xfc00 // unique local, fc00::/7
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_denylists_loopback_and_private() {
+        assert!(is_denylisted("127.0.0.1".parse().unwrap()));
+        assert!(is_denylisted("10.0.0.5".parse().unwrap()));
+        assert!(is_denylisted("192.168.1.1".parse().unwrap()));
+        assert!(is_denylisted("169.254.1.1".parse().unwrap()));
+        assert!(is_denylisted("::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_allows_public_address() {
+        assert!(!is_denylisted("93.184.216.34".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rejects_unsupported_scheme() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let capture = DownloadCapture {
+            storage: Arc::new(FileStorage::new(dir.path().to_path_buf())),
+            logger: Arc::new(SessionLogger::new(None)),
+            max_bytes: 1024,
+        };
+
+        match capture.fetch("ftp://example.com/file").await {
+            FetchOutcome::Refused(reason) => assert!(reason.contains("scheme")),
+            _ => panic!("expected the ftp:// URL to be refused"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_refuses_loopback_target() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let capture = DownloadCapture {
+            storage: Arc::new(FileStorage::new(dir.path().to_path_buf())),
+            logger: Arc::new(SessionLogger::new(None)),
+            max_bytes: 1024,
+        };
+
+        match capture.fetch("http://127.0.0.1:9/secret").await {
+            FetchOutcome::Refused(reason) => assert!(reason.contains("non-public")),
+            _ => panic!("expected the loopback URL to be refused"),
+        }
+    }
+}
@@ -1,35 +1,119 @@
-//! Fake in-memory filesystem
+//! Fake filesystem: a metadata-aware, copy-on-write session view layered
+//! over a shared, read-only base image
+//!
+//! The base image (`FilesystemImage`) is built once, from a configured
+//! `FilesystemProfile` or the built-in decoy tree, and shared via `Arc`
+//! across every session. Each session's `FakeFilesystem` only ever mutates
+//! its own overlay, so writes, new directories, and deletions persist for
+//! the rest of that attacker's session without touching the canonical image
+//! or leaking into anyone else's session.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-/// Fake filesystem that exists only in memory
-pub struct FakeFilesystem {
-    files: HashMap<PathBuf, String>,
-    dirs: Vec<PathBuf>,
+use chrono::{DateTime, Utc};
+
+use crate::config::{FakeFileEntry, FilesystemProfile};
+
+/// Default permission bits for a newly created regular file (rw-r--r--)
+const DEFAULT_FILE_MODE: u32 = 0o644;
+/// Default permission bits for a newly created directory (rwxr-xr-x)
+const DEFAULT_DIR_MODE: u32 = 0o755;
+
+/// What kind of node a path refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    File,
+    Dir,
+    Symlink,
 }
 
-impl FakeFilesystem {
-    /// Create a new fake filesystem with common Linux directory structure
-    pub fn new() -> Self {
-        let mut fs = Self {
-            files: HashMap::new(),
-            dirs: Vec::new(),
-        };
+/// `stat`/`ls -l` style metadata for one filesystem node
+#[derive(Debug, Clone)]
+pub struct Metadata {
+    pub kind: NodeKind,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u64,
+    pub mtime: DateTime<Utc>,
+}
+
+/// One node in the fake filesystem. `content` holds file contents for a
+/// `File`, or the link target for a `Symlink`; it is unused for a `Dir`.
+#[derive(Debug, Clone)]
+struct Node {
+    metadata: Metadata,
+    content: String,
+}
+
+impl Node {
+    fn file(content: String, mode: u32, uid: u32, gid: u32, mtime: DateTime<Utc>) -> Self {
+        let size = content.len() as u64;
+        Self {
+            metadata: Metadata { kind: NodeKind::File, mode, uid, gid, size, mtime },
+            content,
+        }
+    }
+
+    fn dir(mode: u32, uid: u32, gid: u32, mtime: DateTime<Utc>) -> Self {
+        Self {
+            metadata: Metadata { kind: NodeKind::Dir, mode, uid, gid, size: 4096, mtime },
+            content: String::new(),
+        }
+    }
+
+    fn symlink(target: String, uid: u32, gid: u32, mtime: DateTime<Utc>) -> Self {
+        let size = target.len() as u64;
+        Self {
+            metadata: Metadata { kind: NodeKind::Symlink, mode: 0o777, uid, gid, size, mtime },
+            content: target,
+        }
+    }
+}
+
+/// The canonical, read-only filesystem image shared across every session.
+/// Built once at startup, wrapped in an `Arc`, and handed to each session's
+/// `FakeFilesystem::with_image`; sessions never mutate it, only their own
+/// copy-on-write overlay.
+pub struct FilesystemImage {
+    nodes: HashMap<PathBuf, Node>,
+}
+
+impl FilesystemImage {
+    /// Build an image from a `FilesystemProfile`: a `base_path` directory
+    /// snapshot takes precedence over an inline `manifest` when both are
+    /// set; if neither is set, falls back to `default_image`.
+    pub fn from_profile(profile: &FilesystemProfile) -> Self {
+        if let Some(base_path) = &profile.base_path {
+            match Self::snapshot_dir(Path::new(base_path)) {
+                Ok(image) => return image,
+                Err(e) => tracing::warn!(
+                    path = %base_path,
+                    error = %e,
+                    "Failed to snapshot filesystem base_path; falling back to manifest/default image"
+                ),
+            }
+        }
 
-        // Create common directories
-        fs.create_dir("/");
-        fs.create_dir("/root");
-        fs.create_dir("/home");
-        fs.create_dir("/etc");
-        fs.create_dir("/var");
-        fs.create_dir("/tmp");
-        fs.create_dir("/usr");
-        fs.create_dir("/bin");
-        fs.create_dir("/sbin");
-
-        // Create common files with realistic content
-        fs.create_file(
+        if !profile.manifest.is_empty() {
+            return Self::from_manifest(&profile.manifest);
+        }
+
+        Self::default_image()
+    }
+
+    /// The built-in decoy tree used when no `FilesystemProfile` is configured
+    pub fn default_image() -> Self {
+        let mut image = Self { nodes: HashMap::new() };
+        let now = Utc::now();
+
+        for dir in ["/", "/root", "/home", "/etc", "/var", "/tmp", "/usr", "/bin", "/sbin"] {
+            image.nodes.insert(PathBuf::from(dir), Node::dir(DEFAULT_DIR_MODE, 0, 0, now));
+        }
+
+        image.insert_file(
             "/etc/passwd",
             "root:x:0:0:root:/root:/bin/bash\n\
              daemon:x:1:1:daemon:/usr/sbin:/usr/sbin/nologin\n\
@@ -38,16 +122,18 @@ impl FakeFilesystem {
              sync:x:4:65534:sync:/bin:/bin/sync\n\
              www-data:x:33:33:www-data:/var/www:/usr/sbin/nologin\n\
              nobody:x:65534:65534:nobody:/nonexistent:/usr/sbin/nologin\n",
+            now,
         );
 
-        fs.create_file(
+        image.insert_file(
             "/etc/shadow",
             "root:$6$rounds=656000$YT...:19000:0:99999:7:::\n\
              daemon:*:18375:0:99999:7:::\n\
              bin:*:18375:0:99999:7:::\n",
+            now,
         );
 
-        fs.create_file(
+        image.insert_file(
             "/etc/hosts",
             "127.0.0.1\tlocalhost\n\
              127.0.1.1\thoneypot\n\
@@ -55,14 +141,12 @@ impl FakeFilesystem {
              ::1     localhost ip6-localhost ip6-loopback\n\
              ff02::1 ip6-allnodes\n\
              ff02::2 ip6-allrouters\n",
+            now,
         );
 
-        fs.create_file(
-            "/etc/hostname",
-            "honeypot\n",
-        );
+        image.insert_file("/etc/hostname", "honeypot\n", now);
 
-        fs.create_file(
+        image.insert_file(
             "/etc/os-release",
             "PRETTY_NAME=\"Ubuntu 22.04.1 LTS\"\n\
              NAME=\"Ubuntu\"\n\
@@ -71,9 +155,10 @@ impl FakeFilesystem {
              VERSION_CODENAME=jammy\n\
              ID=ubuntu\n\
              ID_LIKE=debian\n",
+            now,
         );
 
-        fs.create_file(
+        image.insert_file(
             "/root/.bashrc",
             "# .bashrc\n\
              \n\
@@ -82,81 +167,227 @@ impl FakeFilesystem {
                  *i*) ;;\n\
                    *) return;;\n\
              esac\n",
+            now,
         );
 
-        fs.create_file(
+        image.insert_file(
             "/root/.bash_history",
             "ls -la\n\
              cd /tmp\n\
              wget http://example.com/script.sh\n\
              chmod +x script.sh\n\
              ./script.sh\n",
+            now,
+        );
+
+        image
+    }
+
+    fn insert_file(&mut self, path: &str, content: &str, mtime: DateTime<Utc>) {
+        self.nodes.insert(
+            PathBuf::from(path),
+            Node::file(content.to_string(), DEFAULT_FILE_MODE, 0, 0, mtime),
         );
+    }
+
+    fn from_manifest(entries: &[FakeFileEntry]) -> Self {
+        let mut image = Self { nodes: HashMap::new() };
+        image.nodes.insert(PathBuf::from("/"), Node::dir(DEFAULT_DIR_MODE, 0, 0, Utc::now()));
+
+        for entry in entries {
+            let path = PathBuf::from(&entry.path);
+            image.ensure_parents(&path);
+
+            let mtime = Utc::now();
+            let node = if let Some(target) = &entry.symlink_target {
+                Node::symlink(target.clone(), entry.uid, entry.gid, mtime)
+            } else if entry.is_dir {
+                Node::dir(entry.mode, entry.uid, entry.gid, mtime)
+            } else {
+                Node::file(entry.content.clone().unwrap_or_default(), entry.mode, entry.uid, entry.gid, mtime)
+            };
+            image.nodes.insert(path, node);
+        }
+
+        image
+    }
+
+    fn snapshot_dir(base_path: &Path) -> std::io::Result<Self> {
+        let mut image = Self { nodes: HashMap::new() };
+        image.nodes.insert(PathBuf::from("/"), Node::dir(DEFAULT_DIR_MODE, 0, 0, Utc::now()));
+        image.snapshot_recursive(base_path, Path::new("/"))?;
+        Ok(image)
+    }
+
+    fn snapshot_recursive(&mut self, real_dir: &Path, fake_dir: &Path) -> std::io::Result<()> {
+        for entry in std::fs::read_dir(real_dir)? {
+            let entry = entry?;
+            let real_path = entry.path();
+            let fake_path = fake_dir.join(entry.file_name());
+            let meta = entry.metadata()?;
+            let mtime: DateTime<Utc> = meta.modified().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now());
+
+            #[cfg(unix)]
+            let (mode, uid, gid) = {
+                use std::os::unix::fs::MetadataExt;
+                (meta.mode() & 0o7777, meta.uid(), meta.gid())
+            };
+            #[cfg(not(unix))]
+            let (mode, uid, gid): (u32, u32, u32) =
+                (if meta.is_dir() { DEFAULT_DIR_MODE } else { DEFAULT_FILE_MODE }, 0, 0);
+
+            if meta.is_dir() {
+                self.nodes.insert(fake_path.clone(), Node::dir(mode, uid, gid, mtime));
+                self.snapshot_recursive(&real_path, &fake_path)?;
+            } else {
+                let content = std::fs::read_to_string(&real_path).unwrap_or_default();
+                self.nodes.insert(fake_path, Node::file(content, mode, uid, gid, mtime));
+            }
+        }
+        Ok(())
+    }
+
+    /// Ensure every ancestor of `path` exists as a directory node, so a
+    /// manifest that only lists leaf paths still produces a walkable tree
+    fn ensure_parents(&mut self, path: &Path) {
+        let mut ancestors: Vec<&Path> = path.ancestors().skip(1).collect();
+        ancestors.reverse();
+        for ancestor in ancestors {
+            self.nodes
+                .entry(ancestor.to_path_buf())
+                .or_insert_with(|| Node::dir(DEFAULT_DIR_MODE, 0, 0, Utc::now()));
+        }
+    }
+}
+
+impl Default for FilesystemImage {
+    fn default() -> Self {
+        Self::default_image()
+    }
+}
+
+/// One pending change in a session's copy-on-write overlay
+#[derive(Debug, Clone)]
+enum Overlay {
+    Upsert(Node),
+    Deleted,
+}
 
-        fs
+/// A session's view of the fake filesystem: a shared, read-only base image
+/// plus a private copy-on-write overlay. `write_file`, `create_dir`, and
+/// `remove` only ever touch the overlay, so a session's changes persist for
+/// its own lifetime without mutating the canonical image or being visible
+/// to any other session.
+pub struct FakeFilesystem {
+    base: Arc<FilesystemImage>,
+    overlay: HashMap<PathBuf, Overlay>,
+}
+
+impl FakeFilesystem {
+    /// Create a new fake filesystem using the built-in decoy tree
+    pub fn new() -> Self {
+        Self::with_image(Arc::new(FilesystemImage::default_image()))
     }
 
-    /// Create a directory
-    fn create_dir(&mut self, path: &str) {
-        self.dirs.push(PathBuf::from(path));
+    /// Create a new session view over a shared base image
+    pub fn with_image(base: Arc<FilesystemImage>) -> Self {
+        Self { base, overlay: HashMap::new() }
     }
 
-    /// Create a file with content
-    fn create_file(&mut self, path: &str, content: &str) {
-        self.files.insert(PathBuf::from(path), content.to_string());
+    fn node(&self, path: &Path) -> Option<&Node> {
+        match self.overlay.get(path) {
+            Some(Overlay::Deleted) => None,
+            Some(Overlay::Upsert(node)) => Some(node),
+            None => self.base.nodes.get(path),
+        }
     }
 
     /// Check if a directory exists
     pub fn dir_exists(&self, path: &Path) -> bool {
-        self.dirs.iter().any(|d| d == path)
+        matches!(self.node(path).map(|n| n.metadata.kind), Some(NodeKind::Dir))
+    }
+
+    /// Metadata for a path, if it exists (e.g. for `stat`/`ls -l`)
+    pub fn metadata(&self, path: &Path) -> Option<Metadata> {
+        self.node(path).map(|n| n.metadata.clone())
     }
 
-    /// List directory contents
-    pub fn list_dir(&self, path: &Path) -> Vec<String> {
+    /// List a directory's immediate children with their metadata, merging
+    /// the session overlay over the read-only base
+    pub fn list_dir(&self, path: &Path) -> Vec<(String, Metadata)> {
         let path_str = path.to_string_lossy();
 
-        // Get subdirectories
-        let mut entries: Vec<String> = self
-            .dirs
-            .iter()
-            .filter_map(|d| {
-                let d_str = d.to_string_lossy();
-                if let Some(parent) = d.parent() {
-                    if parent.to_string_lossy() == path_str && d_str != path_str {
-                        d.file_name().map(|n| n.to_string_lossy().to_string())
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
+        let mut candidates: HashSet<PathBuf> = self.base.nodes.keys().cloned().collect();
+        candidates.extend(self.overlay.keys().cloned());
+
+        let mut entries: Vec<(String, Metadata)> = candidates
+            .into_iter()
+            .filter(|p| p != path && p.parent().map(|parent| parent.to_string_lossy() == path_str).unwrap_or(false))
+            .filter_map(|p| {
+                let name = p.file_name()?.to_string_lossy().to_string();
+                let metadata = self.node(&p)?.metadata.clone();
+                Some((name, metadata))
             })
             .collect();
 
-        // Get files in this directory
-        for (file_path, _) in &self.files {
-            if let Some(parent) = file_path.parent() {
-                if parent.to_string_lossy() == path_str {
-                    if let Some(name) = file_path.file_name() {
-                        entries.push(name.to_string_lossy().to_string());
-                    }
-                }
-            }
-        }
-
-        entries.sort();
-        entries.dedup();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
         entries
     }
 
-    /// Read a file
+    /// Read a file's contents
     pub fn read_file(&self, path: &Path) -> Option<&str> {
-        self.files.get(path).map(|s| s.as_str())
+        match self.node(path) {
+            Some(node) if node.metadata.kind == NodeKind::File => Some(node.content.as_str()),
+            _ => None,
+        }
     }
 
-    /// Write a file (for honeypot purposes, we log but don't actually store)
+    /// Write (creating or overwriting) a file in this session's overlay,
+    /// preserving its existing mode/ownership if it already exists
     pub fn write_file(&mut self, path: PathBuf, content: String) {
-        self.files.insert(path, content);
+        let (mode, uid, gid) = self
+            .node(&path)
+            .map(|n| (n.metadata.mode, n.metadata.uid, n.metadata.gid))
+            .unwrap_or((DEFAULT_FILE_MODE, 0, 0));
+        let node = Node::file(content, mode, uid, gid, Utc::now());
+        self.overlay.insert(path, Overlay::Upsert(node));
+    }
+
+    /// Create an empty file if `path` doesn't exist, or just bump its mtime
+    /// if it does, in this session's overlay
+    pub fn touch(&mut self, path: PathBuf) {
+        let node = match self.node(&path).cloned() {
+            Some(mut node) => {
+                node.metadata.mtime = Utc::now();
+                node
+            }
+            None => Node::file(String::new(), DEFAULT_FILE_MODE, 0, 0, Utc::now()),
+        };
+        self.overlay.insert(path, Overlay::Upsert(node));
+    }
+
+    /// Create a directory in this session's overlay
+    pub fn create_dir(&mut self, path: PathBuf) {
+        self.overlay.insert(path, Overlay::Upsert(Node::dir(DEFAULT_DIR_MODE, 0, 0, Utc::now())));
+    }
+
+    /// Delete a path in this session's overlay; the canonical base image and
+    /// other sessions are unaffected
+    pub fn remove(&mut self, path: &Path) {
+        self.overlay.insert(path.to_path_buf(), Overlay::Deleted);
+    }
+
+    /// Paths under `root` this session has written or created, each paired
+    /// with whether it's new relative to the base image (not present there)
+    /// or modifies a path the base image already had. Used by the fake
+    /// `git` subsystem to derive `git status`'s modified/untracked lists
+    /// without this session needing its own index/staging-area concept.
+    pub fn overlay_changes_under(&self, root: &Path) -> Vec<(PathBuf, bool)> {
+        self.overlay
+            .iter()
+            .filter(|(path, change)| path.starts_with(root) && matches!(change, Overlay::Upsert(_)))
+            .map(|(path, _)| (path.clone(), !self.base.nodes.contains_key(path)))
+            .collect()
     }
 }
 
@@ -188,17 +419,84 @@ mod tests {
     #[test]
     fn test_list_root() {
         let fs = FakeFilesystem::new();
-        let entries = fs.list_dir(Path::new("/"));
-        assert!(entries.contains(&"root".to_string()));
-        assert!(entries.contains(&"etc".to_string()));
-        assert!(entries.contains(&"tmp".to_string()));
+        let names: Vec<String> = fs.list_dir(Path::new("/")).into_iter().map(|(n, _)| n).collect();
+        assert!(names.contains(&"root".to_string()));
+        assert!(names.contains(&"etc".to_string()));
+        assert!(names.contains(&"tmp".to_string()));
     }
 
     #[test]
     fn test_list_etc() {
         let fs = FakeFilesystem::new();
-        let entries = fs.list_dir(Path::new("/etc"));
-        assert!(entries.contains(&"passwd".to_string()));
-        assert!(entries.contains(&"hosts".to_string()));
+        let names: Vec<String> = fs.list_dir(Path::new("/etc")).into_iter().map(|(n, _)| n).collect();
+        assert!(names.contains(&"passwd".to_string()));
+        assert!(names.contains(&"hosts".to_string()));
+    }
+
+    #[test]
+    fn test_default_image_metadata_is_realistic() {
+        let fs = FakeFilesystem::new();
+        let metadata = fs.metadata(Path::new("/etc/passwd")).unwrap();
+        assert_eq!(metadata.kind, NodeKind::File);
+        assert_eq!(metadata.mode, 0o644);
+        assert!(metadata.size > 0);
+    }
+
+    #[test]
+    fn test_write_file_does_not_mutate_base_image() {
+        let base = Arc::new(FilesystemImage::default_image());
+        let mut fs = FakeFilesystem::with_image(base.clone());
+
+        fs.write_file(PathBuf::from("/root/notes.txt"), "evidence".to_string());
+        assert_eq!(fs.read_file(Path::new("/root/notes.txt")), Some("evidence"));
+
+        let other_session = FakeFilesystem::with_image(base);
+        assert_eq!(other_session.read_file(Path::new("/root/notes.txt")), None);
+    }
+
+    #[test]
+    fn test_remove_hides_base_file_only_for_this_session() {
+        let base = Arc::new(FilesystemImage::default_image());
+        let mut fs = FakeFilesystem::with_image(base.clone());
+
+        fs.remove(Path::new("/etc/hostname"));
+        assert_eq!(fs.read_file(Path::new("/etc/hostname")), None);
+
+        let other_session = FakeFilesystem::with_image(base);
+        assert!(other_session.read_file(Path::new("/etc/hostname")).is_some());
+    }
+
+    #[test]
+    fn test_create_dir_then_list_shows_new_entry() {
+        let mut fs = FakeFilesystem::new();
+        fs.create_dir(PathBuf::from("/root/uploads"));
+        let names: Vec<String> = fs.list_dir(Path::new("/root")).into_iter().map(|(n, _)| n).collect();
+        assert!(names.contains(&"uploads".to_string()));
+    }
+
+    #[test]
+    fn test_image_from_manifest_creates_missing_parent_dirs() {
+        let profile = FilesystemProfile {
+            base_path: None,
+            manifest: vec![FakeFileEntry {
+                path: "/opt/app/config.yml".to_string(),
+                content: Some("debug: true\n".to_string()),
+                is_dir: false,
+                symlink_target: None,
+                mode: 0o640,
+                uid: 1000,
+                gid: 1000,
+            }],
+        };
+
+        let image = FilesystemImage::from_profile(&profile);
+        let fs = FakeFilesystem::with_image(Arc::new(image));
+
+        assert!(fs.dir_exists(Path::new("/opt")));
+        assert!(fs.dir_exists(Path::new("/opt/app")));
+        assert_eq!(fs.read_file(Path::new("/opt/app/config.yml")), Some("debug: true\n"));
+        let metadata = fs.metadata(Path::new("/opt/app/config.yml")).unwrap();
+        assert_eq!(metadata.mode, 0o640);
+        assert_eq!(metadata.uid, 1000);
     }
 }
@@ -1,18 +1,49 @@
 //! Fake shell environment
 
 mod commands;
+mod dispatcher;
+mod fakegit;
+mod fetch;
 mod filesystem;
+mod parser;
+mod personality;
+mod telemetry;
 
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
 
-use commands::execute_command;
+use commands::{execute_command, resolve_path};
 use filesystem::FakeFilesystem;
+use parser::{Append, Joiner, Pipeline, Segment, Word};
+use telemetry::Recorder;
+
+use crate::config::{Personality, SharedShellConfig};
+
+pub use dispatcher::{CommandContext, Dispatcher, Handler};
+pub use fetch::{DownloadCapture, DownloadCaptureConfig};
+pub use filesystem::FilesystemImage;
+pub use telemetry::CommandEvent;
+
+/// How many `$(...)`/backtick substitutions may nest inside one another
+/// before `run_command_substitution` refuses to recurse further. Each level
+/// is another `execute` call on the Rust call stack, so an attacker-typed
+/// line with thousands of nested substitutions would otherwise overflow
+/// the stack and abort the whole process instead of failing just their own
+/// session.
+const MAX_SUBSTITUTION_DEPTH: usize = 16;
 
 /// Fake shell that emulates a Linux shell environment
 pub struct FakeShell {
     filesystem: FakeFilesystem,
     current_dir: PathBuf,
     env_vars: std::collections::HashMap<String, String>,
+    shell_config: Option<SharedShellConfig>,
+    download_capture: Option<DownloadCapture>,
+    source_ip: Option<String>,
+    dispatcher: Dispatcher,
+    recorder: Option<Recorder>,
+    substitution_depth: usize,
 }
 
 impl FakeShell {
@@ -28,10 +59,98 @@ impl FakeShell {
             filesystem: FakeFilesystem::new(),
             current_dir: PathBuf::from("/root"),
             env_vars,
+            shell_config: None,
+            download_capture: None,
+            source_ip: None,
+            dispatcher: commands::default_dispatcher(),
+            recorder: None,
+            substitution_depth: 0,
+        }
+    }
+
+    /// Create a new fake shell whose personality (hostname, `uname`, banner,
+    /// etc.) tracks a hot-reloadable `ShellConfig` live, deterministically
+    /// chosen from its personality pool by `source_ip`, and whose filesystem
+    /// is a copy-on-write view over the shared `filesystem_image`
+    pub fn with_shell_config(
+        shell_config: SharedShellConfig,
+        filesystem_image: Arc<FilesystemImage>,
+        source_ip: Option<String>,
+    ) -> Self {
+        Self {
+            filesystem: FakeFilesystem::with_image(filesystem_image),
+            shell_config: Some(shell_config),
+            source_ip,
+            ..Self::new()
+        }
+    }
+
+    /// Create a new fake shell whose `wget`/`curl` commands fetch and store
+    /// real payloads instead of returning canned output
+    pub fn with_shell_config_and_capture(
+        shell_config: SharedShellConfig,
+        filesystem_image: Arc<FilesystemImage>,
+        download_capture: DownloadCapture,
+        source_ip: Option<String>,
+    ) -> Self {
+        Self {
+            download_capture: Some(download_capture),
+            ..Self::with_shell_config(shell_config, filesystem_image, source_ip)
         }
     }
 
-    /// Execute a command in the fake shell
+    /// The personality this session presents: chosen deterministically from
+    /// the live config's pool by `source_ip`, or the default identity if
+    /// this shell has no config attached
+    async fn personality(&self) -> Personality {
+        match &self.shell_config {
+            Some(shared) => {
+                let cfg = shared.read().await;
+                personality::choose(self.source_ip.as_deref(), &cfg.personalities).clone()
+            }
+            None => Personality::default(),
+        }
+    }
+
+    /// The welcome banner for this session's personality
+    pub async fn banner(&self) -> String {
+        self.personality().await.banner
+    }
+
+    /// Register a custom builtin, replacing any handler already registered
+    /// under `name`, so deployments can add host-specific commands (custom
+    /// banners, fake services) without editing the core command set
+    pub fn register_command(&mut self, name: impl Into<String>, handler: Handler) {
+        self.dispatcher.register_command(name, handler);
+    }
+
+    /// Register `alias` to resolve to the builtin named `target`, inserting
+    /// `extra_args` ahead of whatever the caller typed, e.g. `ll` -> `ls -l`
+    pub fn register_alias(&mut self, alias: impl Into<String>, target: impl Into<String>, extra_args: Vec<String>) {
+        self.dispatcher.register_alias(alias, target, extra_args);
+    }
+
+    /// Opt into structured telemetry: every future `execute()` call appends
+    /// a `CommandEvent` to `session_log()` and streams it to `sink` as a
+    /// JSON line. Keep `sink` fast — a slow `Write` impl blocks this
+    /// session's async task while it's written.
+    pub fn with_recorder(mut self, sink: Box<dyn std::io::Write + Send>) -> Self {
+        self.recorder = Some(Recorder::with_sink(sink));
+        self
+    }
+
+    /// The commands recorded so far, or an empty slice if telemetry was
+    /// never enabled via `with_recorder`
+    pub fn session_log(&self) -> &[CommandEvent] {
+        self.recorder.as_ref().map(Recorder::events).unwrap_or(&[])
+    }
+
+    /// Execute a command line in the fake shell: tokenizes it into
+    /// pipelines joined by `;`/`&&`/`||`, runs each pipeline's stages
+    /// left-to-right feeding each stage's output into the next as `stdin`,
+    /// and honors `&&`/`||` based on the previous pipeline's exit status.
+    /// If telemetry was enabled via `with_recorder`, also appends a
+    /// `CommandEvent` to `session_log()` for the whole call.
     pub async fn execute(&mut self, command: &str) -> String {
         let command = command.trim();
 
@@ -39,23 +158,192 @@ impl FakeShell {
             return String::new();
         }
 
-        // Parse command (handle basic shell syntax)
-        let parts: Vec<&str> = command.split_whitespace().collect();
-        if parts.is_empty() {
+        let started = Instant::now();
+        let cwd = self.current_dir.display().to_string();
+
+        let pipelines = parser::parse(command);
+        if pipelines.is_empty() {
             return String::new();
         }
 
-        let cmd = parts[0];
-        let args = &parts[1..];
+        let personality = self.personality().await;
+        let mut output = String::new();
+        let mut last_status = 0;
+        let mut argv = Vec::new();
 
-        // Execute the command
-        execute_command(
-            cmd,
-            args,
-            &mut self.filesystem,
-            &mut self.current_dir,
-            &self.env_vars,
-        ).await
+        for pipeline in &pipelines {
+            let should_run = match pipeline.joiner {
+                Joiner::Start | Joiner::Then => true,
+                Joiner::And => last_status == 0,
+                Joiner::Or => last_status != 0,
+            };
+
+            if !should_run {
+                continue;
+            }
+
+            let (pipeline_output, status, pipeline_argv) = self.run_pipeline(pipeline, &personality).await;
+            output.push_str(&pipeline_output);
+            last_status = status;
+            argv.extend(pipeline_argv);
+        }
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(command, argv, cwd, last_status, started.elapsed().as_millis() as u64, &output);
+        }
+
+        output
+    }
+
+    /// Run every stage of one pipeline, threading each stage's output into
+    /// the next as `stdin`, routing the final stage's output to the fake
+    /// filesystem when it ends in a `>`/`>>` redirection, and collecting
+    /// every stage's expanded argv for telemetry
+    async fn run_pipeline(&mut self, pipeline: &Pipeline, personality: &Personality) -> (String, i32, Vec<String>) {
+        let mut next_stdin: Option<String> = None;
+        let mut status = 0;
+        let mut visible_output = String::new();
+        let mut argv = Vec::new();
+
+        for stage in &pipeline.stages {
+            let stdin = match &stage.stdin_file {
+                Some(path) => self.filesystem.read_file(&resolve_path(path, &self.current_dir)).map(str::to_string),
+                None => next_stdin.take(),
+            };
+
+            let cmd = self.expand_word(&stage.cmd).await;
+            let mut args = Vec::with_capacity(stage.args.len());
+            for arg in &stage.args {
+                args.push(self.expand_word(arg).await);
+            }
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+            argv.push(cmd.clone());
+            argv.extend(args.iter().map(|a| a.to_string()));
+
+            let (stage_output, stage_status) = execute_command(
+                &cmd,
+                &args,
+                stdin.as_deref(),
+                &mut self.filesystem,
+                &mut self.current_dir,
+                &self.env_vars,
+                personality,
+                self.download_capture.as_ref(),
+                &self.dispatcher,
+            ).await;
+            status = stage_status;
+
+            if let Some((target, append)) = &stage.stdout_file {
+                let path = resolve_path(target, &self.current_dir);
+                let content = if *append == Append::Append {
+                    format!("{}{}", self.filesystem.read_file(&path).unwrap_or(""), stage_output)
+                } else {
+                    stage_output
+                };
+                self.filesystem.write_file(path, content);
+                next_stdin = Some(String::new());
+                visible_output = String::new();
+            } else {
+                visible_output = stage_output.clone();
+                next_stdin = Some(stage_output);
+            }
+        }
+
+        (visible_output, status, argv)
+    }
+
+    /// Expand one token the way a login shell would before dispatch:
+    /// single-quoted (`Literal`) segments pass through unchanged; bare or
+    /// double-quoted (`Expand`) segments get `$VAR`/`${VAR}` substitution,
+    /// `$(...)`/backtick command substitution, and, for the first segment
+    /// only, a leading `~` expanded to `$HOME`
+    async fn expand_word(&mut self, word: &Word) -> String {
+        let mut result = String::new();
+
+        for (index, segment) in word.iter().enumerate() {
+            match segment {
+                Segment::Literal(text) => result.push_str(text),
+                Segment::Expand(text) => {
+                    let text = if index == 0 && (text == "~" || text.starts_with("~/")) {
+                        let home = self.env_vars.get("HOME").cloned().unwrap_or_default();
+                        format!("{}{}", home, &text[1..])
+                    } else {
+                        text.clone()
+                    };
+                    let expanded = self.expand_text(&text).await;
+                    result.push_str(&expanded);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Scan `text` for `$VAR`/`${VAR}` references and `$(...)`/backtick
+    /// command substitutions, replacing each in place
+    async fn expand_text(&mut self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut out = String::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+
+            if c == '$' && chars.get(i + 1) == Some(&'(') {
+                if let Some(end) = parser::matching_paren(&chars, i + 1) {
+                    let inner: String = chars[i + 2..end].iter().collect();
+                    let substituted = self.run_command_substitution(&inner).await;
+                    out.push_str(substituted.trim_end_matches('\n'));
+                    i = end + 1;
+                    continue;
+                }
+            }
+
+            if c == '`' {
+                if let Some(end) = chars[i + 1..].iter().position(|&ch| ch == '`').map(|p| i + 1 + p) {
+                    let inner: String = chars[i + 1..end].iter().collect();
+                    let substituted = self.run_command_substitution(&inner).await;
+                    out.push_str(substituted.trim_end_matches('\n'));
+                    i = end + 1;
+                    continue;
+                }
+            }
+
+            if c == '$' {
+                let (name, consumed) = read_var_name(&chars, i + 1);
+                if !name.is_empty() {
+                    if let Some(value) = self.env_vars.get(&name) {
+                        out.push_str(value);
+                    }
+                    i += 1 + consumed;
+                    continue;
+                }
+            }
+
+            out.push(c);
+            i += 1;
+        }
+
+        out
+    }
+
+    /// Recursively run `command` in this same shell for `$(...)`/backtick
+    /// substitution; boxed to break the otherwise-infinite
+    /// `execute` -> ... -> `expand_text` -> `execute` async recursion.
+    /// Refuses to recurse past `MAX_SUBSTITUTION_DEPTH` so a deeply nested
+    /// substitution can't overflow the stack.
+    fn run_command_substitution<'a>(&'a mut self, command: &'a str) -> std::pin::Pin<Box<dyn std::future::Future<Output = String> + Send + 'a>> {
+        Box::pin(async move {
+            if self.substitution_depth >= MAX_SUBSTITUTION_DEPTH {
+                return "bash: command substitution: too much recursion\n".to_string();
+            }
+
+            self.substitution_depth += 1;
+            let output = self.execute(command).await;
+            self.substitution_depth -= 1;
+            output
+        })
     }
 
     /// Get current working directory
@@ -70,6 +358,24 @@ impl Default for FakeShell {
     }
 }
 
+/// Read a `$VAR`/`${VAR}` reference starting just after the `$` at
+/// `chars[start]`, returning its name and the number of characters consumed
+/// (0 if `start` doesn't begin a valid variable reference)
+fn read_var_name(chars: &[char], start: usize) -> (String, usize) {
+    if chars.get(start) == Some(&'{') {
+        return match chars[start + 1..].iter().position(|&c| c == '}') {
+            Some(len) => (chars[start + 1..start + 1 + len].iter().collect(), len + 2),
+            None => (String::new(), 0),
+        };
+    }
+
+    let len = chars[start..]
+        .iter()
+        .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+        .count();
+    (chars[start..start + len].iter().collect(), len)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -93,4 +399,172 @@ mod tests {
         let output = shell.execute("whoami").await;
         assert_eq!(output.trim(), "root");
     }
+
+    #[tokio::test]
+    async fn test_semicolon_chains_always_run() {
+        let mut shell = FakeShell::new();
+        let output = shell.execute("pwd; whoami").await;
+        assert_eq!(output, "/root\nroot\n");
+    }
+
+    #[tokio::test]
+    async fn test_and_chain_skips_after_failure() {
+        let mut shell = FakeShell::new();
+        let output = shell.execute("cd /nope && whoami").await;
+        assert!(output.contains("No such file or directory"));
+        assert!(!output.contains("root\n"));
+    }
+
+    #[tokio::test]
+    async fn test_or_chain_runs_only_after_failure() {
+        let mut shell = FakeShell::new();
+        let output = shell.execute("cd /nope || whoami").await;
+        assert!(output.contains("No such file or directory"));
+        assert!(output.contains("root\n"));
+    }
+
+    #[tokio::test]
+    async fn test_pipe_feeds_previous_output_as_stdin() {
+        let mut shell = FakeShell::new();
+        let output = shell.execute("echo hello | cat").await;
+        assert_eq!(output.trim(), "hello");
+    }
+
+    #[tokio::test]
+    async fn test_redirect_writes_output_to_filesystem_instead_of_terminal() {
+        let mut shell = FakeShell::new();
+        let output = shell.execute("whoami > /tmp/out").await;
+        assert_eq!(output, "");
+
+        let saved = shell.execute("cat /tmp/out").await;
+        assert_eq!(saved.trim(), "root");
+    }
+
+    #[tokio::test]
+    async fn test_bare_variable_expansion() {
+        let mut shell = FakeShell::new();
+        let output = shell.execute("echo $USER").await;
+        assert_eq!(output.trim(), "root");
+    }
+
+    #[tokio::test]
+    async fn test_braced_variable_expansion() {
+        let mut shell = FakeShell::new();
+        let output = shell.execute("echo ${USER}").await;
+        assert_eq!(output.trim(), "root");
+    }
+
+    #[tokio::test]
+    async fn test_unset_variable_expands_to_empty_string() {
+        let mut shell = FakeShell::new();
+        let output = shell.execute("echo [$NOPE]").await;
+        assert_eq!(output.trim(), "[]");
+    }
+
+    #[tokio::test]
+    async fn test_single_quoted_variable_is_not_expanded() {
+        let mut shell = FakeShell::new();
+        let output = shell.execute("echo '$USER'").await;
+        assert_eq!(output.trim(), "$USER");
+    }
+
+    #[tokio::test]
+    async fn test_double_quoted_variable_is_expanded() {
+        let mut shell = FakeShell::new();
+        let output = shell.execute(r#"echo "$USER""#).await;
+        assert_eq!(output.trim(), "root");
+    }
+
+    #[tokio::test]
+    async fn test_command_substitution_expands_to_inner_command_output() {
+        let mut shell = FakeShell::new();
+        let output = shell.execute("echo $(whoami)").await;
+        assert_eq!(output.trim(), "root");
+    }
+
+    #[tokio::test]
+    async fn test_backtick_substitution_expands_to_inner_command_output() {
+        let mut shell = FakeShell::new();
+        let output = shell.execute("echo `whoami`").await;
+        assert_eq!(output.trim(), "root");
+    }
+
+    #[tokio::test]
+    async fn test_deeply_nested_command_substitution_is_rejected() {
+        let mut shell = FakeShell::new();
+
+        let mut command = "whoami".to_string();
+        for _ in 0..MAX_SUBSTITUTION_DEPTH + 5 {
+            command = format!("echo $({})", command);
+        }
+
+        let output = shell.execute(&command).await;
+        assert!(output.contains("too much recursion"));
+    }
+
+    #[tokio::test]
+    async fn test_tilde_expands_to_home_at_start_of_word() {
+        let mut shell = FakeShell::new();
+        let output = shell.execute("echo ~/bin").await;
+        assert_eq!(output.trim(), "/root/bin");
+    }
+
+    #[tokio::test]
+    async fn test_tilde_mid_word_is_not_expanded() {
+        let mut shell = FakeShell::new();
+        let output = shell.execute("echo a~b").await;
+        assert_eq!(output.trim(), "a~b");
+    }
+
+    #[tokio::test]
+    async fn test_ll_alias_runs_ls_with_extra_args() {
+        let mut shell = FakeShell::new();
+        let ll = shell.execute("ll").await;
+        let ls_l = shell.execute("ls -l").await;
+        assert_eq!(ll, ls_l);
+    }
+
+    #[tokio::test]
+    async fn test_register_command_adds_host_specific_builtin() {
+        let mut shell = FakeShell::new();
+        shell.register_command("motd", Arc::new(|_ctx, _args| "Welcome to the lab\n".to_string()));
+        let output = shell.execute("motd").await;
+        assert_eq!(output, "Welcome to the lab\n");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_command_reports_bash_style_not_found() {
+        let mut shell = FakeShell::new();
+        let output = shell.execute("frobnicate").await;
+        assert_eq!(output, "bash: frobnicate: command not found\n");
+    }
+
+    #[tokio::test]
+    async fn test_session_log_is_empty_without_a_recorder() {
+        let mut shell = FakeShell::new();
+        shell.execute("whoami").await;
+        assert!(shell.session_log().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_with_recorder_captures_command_event_fields() {
+        let mut shell = FakeShell::new().with_recorder(Box::new(Vec::new()));
+        let output = shell.execute("echo hi there").await;
+
+        let events = shell.session_log();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].command, "echo hi there");
+        assert_eq!(events[0].argv, vec!["echo".to_string(), "hi".to_string(), "there".to_string()]);
+        assert_eq!(events[0].cwd, "/root");
+        assert_eq!(events[0].exit_status, 0);
+        assert_eq!(events[0].output, output);
+    }
+
+    #[tokio::test]
+    async fn test_with_recorder_records_one_event_per_execute_call() {
+        let mut shell = FakeShell::new().with_recorder(Box::new(Vec::new()));
+        shell.execute("pwd").await;
+        shell.execute("whoami").await;
+        assert_eq!(shell.session_log().len(), 2);
+    }
 }
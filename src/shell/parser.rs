@@ -0,0 +1,457 @@
+//! Tokenizes a shell command line into pipelines, so `execute` can emulate
+//! `;`, `&&`, `||`, `|`, and `>`/`>>`/`<`/`2>` the way a real shell would
+//! instead of treating the whole line as one command + arguments
+//!
+//! Quoting is preserved per-word as [`Segment`]s rather than flattened to
+//! plain text, so the expansion pass in `FakeShell::execute` can tell a
+//! single-quoted (inert) span from a bare or double-quoted (expandable) one.
+
+/// A lexical token produced by [`tokenize`]. A `$(...)`/backtick command
+/// substitution is captured as one atomic span within its word's text
+/// (embedded whitespace doesn't split it), left for the expansion pass to
+/// evaluate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(Word),
+    Semi,
+    And,
+    Or,
+    Pipe,
+    Gt,
+    DGt,
+    Lt,
+    ErrGt,
+}
+
+/// One piece of a word as written on the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// Single-quoted text: never expanded, matching bash's single-quote
+    /// semantics
+    Literal(String),
+    /// Bare or double-quoted text: subject to `$VAR`/`${VAR}`,
+    /// `$(...)`/backtick, and leading-`~` expansion
+    Expand(String),
+}
+
+/// A word built from one or more quoted/unquoted spans, e.g. `$HOME'/bin'`
+/// is `[Expand("$HOME"), Literal("/bin")]`
+pub type Word = Vec<Segment>;
+
+/// Whether a `>`/`>>` redirection truncates or appends to its target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Append {
+    Truncate,
+    Append,
+}
+
+/// How a [`Pipeline`] is joined to the one before it on the same command
+/// line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Joiner {
+    /// The first pipeline on the line; always runs
+    Start,
+    /// `;` — runs regardless of the previous pipeline's exit status
+    Then,
+    /// `&&` — runs only if the previous pipeline exited 0
+    And,
+    /// `||` — runs only if the previous pipeline exited non-zero
+    Or,
+}
+
+/// One command within a pipeline, with its redirections attached directly
+/// to it (as a real shell parses `cmd args > out 2> err < in`). Redirection
+/// targets aren't expanded by this emulator, so they're kept as plain text.
+#[derive(Debug, Clone, Default)]
+pub struct Stage {
+    pub cmd: Word,
+    pub args: Vec<Word>,
+    pub stdin_file: Option<String>,
+    pub stdout_file: Option<(String, Append)>,
+    pub stderr_file: Option<String>,
+}
+
+/// A sequence of stages connected by unquoted `|`, e.g. `cat f | grep x`
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    pub joiner: Joiner,
+    pub stages: Vec<Stage>,
+}
+
+/// Parse a full command line into the pipelines that make it up, in
+/// left-to-right execution order
+pub fn parse(line: &str) -> Vec<Pipeline> {
+    let tokens = tokenize(line);
+
+    let mut pipelines = Vec::new();
+    let mut stages: Vec<Vec<Token>> = Vec::new();
+    let mut current_stage: Vec<Token> = Vec::new();
+    let mut joiner = Joiner::Start;
+
+    for token in tokens {
+        match token {
+            Token::Pipe => stages.push(std::mem::take(&mut current_stage)),
+            Token::Semi | Token::And | Token::Or => {
+                stages.push(std::mem::take(&mut current_stage));
+                push_pipeline(&mut pipelines, joiner, std::mem::take(&mut stages));
+                joiner = match token {
+                    Token::Semi => Joiner::Then,
+                    Token::And => Joiner::And,
+                    Token::Or => Joiner::Or,
+                    _ => unreachable!(),
+                };
+            }
+            other => current_stage.push(other),
+        }
+    }
+    stages.push(current_stage);
+    push_pipeline(&mut pipelines, joiner, stages);
+
+    pipelines
+}
+
+/// Build a `Pipeline` from its raw stage token-groups and append it, unless
+/// it's an empty pipeline produced by e.g. a trailing `;` or a blank line
+fn push_pipeline(pipelines: &mut Vec<Pipeline>, joiner: Joiner, stages: Vec<Vec<Token>>) {
+    let stages: Vec<Stage> = stages.into_iter().map(build_stage).filter(|s| !s.cmd.is_empty()).collect();
+    if !stages.is_empty() {
+        pipelines.push(Pipeline { joiner, stages });
+    }
+}
+
+/// Turn one stage's tokens into a `Stage`: the first word is the command,
+/// the rest (minus anything consumed by a redirection operator) are its
+/// arguments
+fn build_stage(tokens: Vec<Token>) -> Stage {
+    let mut words: Vec<Word> = Vec::new();
+    let mut stage = Stage::default();
+
+    let mut iter = tokens.into_iter();
+    while let Some(token) = iter.next() {
+        match token {
+            Token::Word(word) => words.push(word),
+            Token::Gt => stage.stdout_file = next_word(&mut iter).map(|w| (w, Append::Truncate)),
+            Token::DGt => stage.stdout_file = next_word(&mut iter).map(|w| (w, Append::Append)),
+            Token::Lt => stage.stdin_file = next_word(&mut iter),
+            Token::ErrGt => stage.stderr_file = next_word(&mut iter),
+            Token::Semi | Token::And | Token::Or | Token::Pipe => {
+                unreachable!("split out before build_stage")
+            }
+        }
+    }
+
+    if !words.is_empty() {
+        stage.cmd = words.remove(0);
+        stage.args = words;
+    }
+    stage
+}
+
+/// Consume the `Word` immediately following a redirection operator, e.g.
+/// the `out.txt` in `> out.txt`, flattened to plain text: this emulator
+/// doesn't expand redirection targets
+fn next_word(iter: &mut impl Iterator<Item = Token>) -> Option<String> {
+    match iter.next() {
+        Some(Token::Word(word)) => Some(raw_text(&word)),
+        _ => None,
+    }
+}
+
+/// Flatten a `Word` back to plain text, ignoring its quoting; used where
+/// this emulator intentionally doesn't expand (redirection targets, and
+/// parser-level tests)
+fn raw_text(word: &Word) -> String {
+    word.iter()
+        .map(|segment| match segment {
+            Segment::Literal(text) | Segment::Expand(text) => text.as_str(),
+        })
+        .collect()
+}
+
+/// Find the index of the `)` matching the `(` at `chars[open]`, accounting
+/// for nested parens; used both while lexing (to keep `$(...)` together as
+/// one token) and again by the expansion pass (to find where a captured
+/// `$(...)` span ends)
+pub(crate) fn matching_paren(chars: &[char], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (offset, &c) in chars[open..].iter().enumerate() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + offset);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// If `chars[i]` begins a `$(...)` or backtick command substitution,
+/// consume the whole balanced span so embedded whitespace doesn't split
+/// the token, and return the index just past it plus the raw matched text
+/// (substitution markers included, for the expansion pass to recognize)
+fn consume_substitution(chars: &[char], i: usize) -> Option<(usize, String)> {
+    if chars.get(i) == Some(&'$') && chars.get(i + 1) == Some(&'(') {
+        return match matching_paren(chars, i + 1) {
+            Some(end) => Some((end + 1, chars[i..=end].iter().collect())),
+            None => Some((chars.len(), chars[i..].iter().collect())),
+        };
+    }
+
+    if chars.get(i) == Some(&'`') {
+        let end = chars[i + 1..].iter().position(|&c| c == '`').map(|p| i + 1 + p);
+        return match end {
+            Some(end) => Some((end + 1, chars[i..=end].iter().collect())),
+            None => Some((chars.len(), chars[i..].iter().collect())),
+        };
+    }
+
+    None
+}
+
+/// Lex a raw command line into tokens, honoring single/double quoting so
+/// operator characters inside quotes are treated as plain text, and
+/// keeping each word's quoted/unquoted spans as separate `Segment`s
+fn tokenize(input: &str) -> Vec<Token> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut word: Word = Vec::new();
+    let mut buf = String::new();
+    let mut buf_is_literal = false;
+    let mut in_word = false;
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut i = 0;
+
+    macro_rules! flush_buf {
+        () => {
+            if !buf.is_empty() {
+                let text = std::mem::take(&mut buf);
+                word.push(if buf_is_literal { Segment::Literal(text) } else { Segment::Expand(text) });
+            }
+        };
+    }
+    macro_rules! flush_word {
+        () => {
+            flush_buf!();
+            if in_word {
+                tokens.push(Token::Word(std::mem::take(&mut word)));
+                in_word = false;
+            }
+        };
+    }
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if in_single {
+            if c == '\'' {
+                in_single = false;
+                flush_buf!();
+                buf_is_literal = false;
+            } else {
+                buf.push(c);
+            }
+            i += 1;
+            continue;
+        }
+
+        if let Some((next_i, text)) = consume_substitution(&chars, i) {
+            buf.push_str(&text);
+            in_word = true;
+            i = next_i;
+            continue;
+        }
+
+        if in_double {
+            if c == '"' {
+                in_double = false;
+            } else {
+                buf.push(c);
+            }
+            i += 1;
+            continue;
+        }
+
+        match c {
+            '\'' => {
+                flush_buf!();
+                buf_is_literal = true;
+                in_single = true;
+                in_word = true;
+                i += 1;
+            }
+            '"' => {
+                in_double = true;
+                in_word = true;
+                i += 1;
+            }
+            ' ' | '\t' => {
+                flush_word!();
+                i += 1;
+            }
+            ';' => {
+                flush_word!();
+                tokens.push(Token::Semi);
+                i += 1;
+            }
+            '|' => {
+                flush_word!();
+                if chars.get(i + 1) == Some(&'|') {
+                    tokens.push(Token::Or);
+                    i += 2;
+                } else {
+                    tokens.push(Token::Pipe);
+                    i += 1;
+                }
+            }
+            '&' if chars.get(i + 1) == Some(&'&') => {
+                flush_word!();
+                tokens.push(Token::And);
+                i += 2;
+            }
+            '>' => {
+                flush_word!();
+                if chars.get(i + 1) == Some(&'>') {
+                    tokens.push(Token::DGt);
+                    i += 2;
+                } else {
+                    tokens.push(Token::Gt);
+                    i += 1;
+                }
+            }
+            '<' => {
+                flush_word!();
+                tokens.push(Token::Lt);
+                i += 1;
+            }
+            '2' if !in_word && chars.get(i + 1) == Some(&'>') => {
+                tokens.push(Token::ErrGt);
+                i += 2;
+            }
+            _ => {
+                buf.push(c);
+                in_word = true;
+                i += 1;
+            }
+        }
+    }
+    flush_word!();
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cmds(pipelines: &[Pipeline]) -> Vec<Vec<String>> {
+        pipelines
+            .iter()
+            .flat_map(|p| &p.stages)
+            .map(|s| std::iter::once(raw_text(&s.cmd)).chain(s.args.iter().map(raw_text)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_simple_command() {
+        let pipelines = parse("ls -la");
+        assert_eq!(cmds(&pipelines), vec![vec!["ls".to_string(), "-la".to_string()]]);
+    }
+
+    #[test]
+    fn test_quoted_pipe_stays_one_token() {
+        let pipelines = parse(r#"echo "a | b""#);
+        assert_eq!(pipelines.len(), 1);
+        assert_eq!(pipelines[0].stages.len(), 1);
+        assert_eq!(raw_text(&pipelines[0].stages[0].args[0]), "a | b");
+    }
+
+    #[test]
+    fn test_pipeline_splits_on_pipe() {
+        let pipelines = parse("cat /etc/passwd | grep root");
+        assert_eq!(pipelines.len(), 1);
+        assert_eq!(pipelines[0].stages.len(), 2);
+        assert_eq!(raw_text(&pipelines[0].stages[0].cmd), "cat");
+        assert_eq!(raw_text(&pipelines[0].stages[1].cmd), "grep");
+    }
+
+    #[test]
+    fn test_and_or_semi_joiners() {
+        let pipelines = parse("false && echo a || echo b; echo c");
+        assert_eq!(pipelines.len(), 4);
+        assert_eq!(pipelines[0].joiner, Joiner::Start);
+        assert_eq!(pipelines[1].joiner, Joiner::And);
+        assert_eq!(pipelines[2].joiner, Joiner::Or);
+        assert_eq!(pipelines[3].joiner, Joiner::Then);
+    }
+
+    #[test]
+    fn test_redirections_are_attached_to_their_stage() {
+        let pipelines = parse("cat /etc/passwd | grep root > /tmp/out");
+        let stage = &pipelines[0].stages[1];
+        assert_eq!(raw_text(&stage.cmd), "grep");
+        assert_eq!(stage.stdout_file, Some(("/tmp/out".to_string(), Append::Truncate)));
+    }
+
+    #[test]
+    fn test_append_redirect() {
+        let pipelines = parse("echo hi >> /tmp/out");
+        assert_eq!(pipelines[0].stages[0].stdout_file, Some(("/tmp/out".to_string(), Append::Append)));
+    }
+
+    #[test]
+    fn test_stdin_and_stderr_redirect() {
+        let pipelines = parse("sort < /tmp/in 2> /tmp/err");
+        let stage = &pipelines[0].stages[0];
+        assert_eq!(stage.stdin_file, Some("/tmp/in".to_string()));
+        assert_eq!(stage.stderr_file, Some("/tmp/err".to_string()));
+    }
+
+    #[test]
+    fn test_blank_and_trailing_separators_produce_no_empty_pipelines() {
+        assert!(parse("").is_empty());
+        assert!(parse("  ").is_empty());
+        assert_eq!(parse("ls;").len(), 1);
+    }
+
+    #[test]
+    fn test_single_quotes_produce_a_literal_segment() {
+        let pipelines = parse(r#"echo '$HOME'"#);
+        assert_eq!(pipelines[0].stages[0].args[0], vec![Segment::Literal("$HOME".to_string())]);
+    }
+
+    #[test]
+    fn test_bare_and_double_quoted_text_produce_expand_segments() {
+        let pipelines = parse(r#"echo $HOME "$USER""#);
+        assert_eq!(pipelines[0].stages[0].args[0], vec![Segment::Expand("$HOME".to_string())]);
+        assert_eq!(pipelines[0].stages[0].args[1], vec![Segment::Expand("$USER".to_string())]);
+    }
+
+    #[test]
+    fn test_concatenated_quoted_and_unquoted_spans_form_one_word() {
+        let pipelines = parse(r#"echo $HOME'/bin'"#);
+        assert_eq!(pipelines[0].stages[0].args.len(), 1);
+        assert_eq!(
+            pipelines[0].stages[0].args[0],
+            vec![Segment::Expand("$HOME".to_string()), Segment::Literal("/bin".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_command_substitution_span_keeps_embedded_whitespace_in_one_word() {
+        let pipelines = parse("echo $(ls -la /tmp)");
+        assert_eq!(pipelines[0].stages[0].args.len(), 1);
+        assert_eq!(raw_text(&pipelines[0].stages[0].args[0]), "$(ls -la /tmp)");
+    }
+
+    #[test]
+    fn test_backtick_substitution_span_keeps_embedded_whitespace_in_one_word() {
+        let pipelines = parse("echo `id -u root`");
+        assert_eq!(pipelines[0].stages[0].args.len(), 1);
+        assert_eq!(raw_text(&pipelines[0].stages[0].args[0]), "`id -u root`");
+    }
+}
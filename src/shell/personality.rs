@@ -0,0 +1,59 @@
+//! Deterministic selection of a host [`Personality`] for a session, so the
+//! same source IP always sees the same identity across repeat visits while
+//! different attackers see varied `uname`/`ifconfig`/banner output instead
+//! of one invariant fingerprint.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::config::Personality;
+
+/// Choose one personality from `pool` for `source_ip`, hashing the address
+/// so repeat visitors get a stable identity across sessions. Panics if
+/// `pool` is empty; config validation guarantees it never is in practice.
+pub fn choose<'a>(source_ip: Option<&str>, pool: &'a [Personality]) -> &'a Personality {
+    assert!(!pool.is_empty(), "personality pool must not be empty");
+
+    let mut hasher = DefaultHasher::new();
+    source_ip.unwrap_or("unknown").hash(&mut hasher);
+    let index = (hasher.finish() as usize) % pool.len();
+    &pool[index]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool() -> Vec<Personality> {
+        (0..3)
+            .map(|i| Personality {
+                hostname: format!("host-{i}"),
+                kernel_release: String::new(),
+                kernel_version: String::new(),
+                banner: String::new(),
+                interface_name: String::new(),
+                mac_address: String::new(),
+                ip_address: String::new(),
+                passwd: String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_choose_is_deterministic_per_ip() {
+        let pool = pool();
+        let first = choose(Some("203.0.113.5"), &pool).hostname.clone();
+        let second = choose(Some("203.0.113.5"), &pool).hostname.clone();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_choose_varies_across_ips() {
+        let pool = pool();
+        let hostnames: std::collections::HashSet<_> = ["1.1.1.1", "2.2.2.2", "3.3.3.3", "4.4.4.4", "5.5.5.5"]
+            .iter()
+            .map(|ip| choose(Some(ip), &pool).hostname.clone())
+            .collect();
+        assert!(hostnames.len() > 1, "expected different IPs to map to more than one profile");
+    }
+}
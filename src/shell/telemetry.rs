@@ -0,0 +1,155 @@
+//! Opt-in, structured telemetry for every command a session runs, so a
+//! defender reviewing a session afterward has a replayable, machine-parseable
+//! record instead of just the rendered transcript
+
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Output longer than this is truncated before being recorded, so a
+/// runaway `cat` of a huge file doesn't blow up the in-memory session log
+const MAX_RECORDED_OUTPUT_BYTES: usize = 4096;
+
+/// One command's execution, recorded for downstream analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandEvent {
+    /// Wall-clock time the command was invoked
+    pub timestamp: DateTime<Utc>,
+    /// Milliseconds since this session's recorder was first used; a
+    /// monotonic complement to `timestamp` that isn't subject to
+    /// wall-clock adjustments
+    pub monotonic_ms: u64,
+    /// The raw command line as typed, before tokenizing or expansion
+    pub command: String,
+    /// The expanded argv actually dispatched, flattened across every
+    /// pipeline stage in execution order
+    pub argv: Vec<String>,
+    /// Working directory at the time the command was invoked
+    pub cwd: String,
+    /// Best-effort exit status of the last pipeline in the command line
+    pub exit_status: i32,
+    /// How long the command took to run
+    pub latency_ms: u64,
+    /// The command's rendered output, truncated to
+    /// `MAX_RECORDED_OUTPUT_BYTES`
+    pub output: String,
+}
+
+/// Truncate `output` to `MAX_RECORDED_OUTPUT_BYTES`, appending a marker so
+/// it's clear from the recorded event itself that it was cut short
+fn truncate_output(output: &str) -> String {
+    if output.len() <= MAX_RECORDED_OUTPUT_BYTES {
+        return output.to_string();
+    }
+
+    let mut end = MAX_RECORDED_OUTPUT_BYTES;
+    while !output.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}... [truncated]", &output[..end])
+}
+
+/// Accumulates `CommandEvent`s for one session and, if given a sink,
+/// streams each one out as a JSON line as soon as it's recorded
+pub struct Recorder {
+    events: Vec<CommandEvent>,
+    epoch: OnceLock<Instant>,
+    sink: Option<Box<dyn std::io::Write + Send>>,
+}
+
+impl Recorder {
+    /// A recorder that streams each event as a JSON line to `sink` as well
+    /// as keeping it in `events()`
+    pub fn with_sink(sink: Box<dyn std::io::Write + Send>) -> Self {
+        Self { events: Vec::new(), epoch: OnceLock::new(), sink: Some(sink) }
+    }
+
+    /// Milliseconds since this recorder's first recorded event; the epoch
+    /// is established lazily so a session that never records anything
+    /// never starts a clock it doesn't need
+    fn monotonic_ms(&self) -> u64 {
+        let epoch = self.epoch.get_or_init(Instant::now);
+        epoch.elapsed().as_millis() as u64
+    }
+
+    /// Record one command's execution, truncating its output and, if a
+    /// sink is attached, writing it out as a JSON line
+    pub fn record(&mut self, command: &str, argv: Vec<String>, cwd: String, exit_status: i32, latency_ms: u64, output: &str) {
+        let event = CommandEvent {
+            timestamp: Utc::now(),
+            monotonic_ms: self.monotonic_ms(),
+            command: command.to_string(),
+            argv,
+            cwd,
+            exit_status,
+            latency_ms,
+            output: truncate_output(output),
+        };
+
+        if let Some(sink) = &mut self.sink {
+            if let Ok(line) = serde_json::to_string(&event) {
+                let _ = writeln!(sink, "{}", line);
+            }
+        }
+
+        self.events.push(event);
+    }
+
+    /// The events recorded so far
+    pub fn events(&self) -> &[CommandEvent] {
+        &self.events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_appends_event_with_given_fields() {
+        let mut recorder = Recorder::with_sink(Box::new(Vec::new()));
+        recorder.record("whoami", vec!["whoami".to_string()], "/root".to_string(), 0, 5, "root\n");
+
+        let events = recorder.events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].command, "whoami");
+        assert_eq!(events[0].argv, vec!["whoami".to_string()]);
+        assert_eq!(events[0].cwd, "/root");
+        assert_eq!(events[0].exit_status, 0);
+        assert_eq!(events[0].latency_ms, 5);
+        assert_eq!(events[0].output, "root\n");
+    }
+
+    #[test]
+    fn test_output_longer_than_limit_is_truncated() {
+        let mut recorder = Recorder::with_sink(Box::new(Vec::new()));
+        let huge = "x".repeat(MAX_RECORDED_OUTPUT_BYTES + 100);
+        recorder.record("cat big", vec![], "/root".to_string(), 0, 1, &huge);
+
+        let recorded = &recorder.events()[0].output;
+        assert!(recorded.len() < huge.len());
+        assert!(recorded.ends_with("... [truncated]"));
+    }
+
+    #[test]
+    fn test_sink_receives_one_json_line_per_event() {
+        let sink: Vec<u8> = Vec::new();
+        let mut recorder = Recorder::with_sink(Box::new(sink));
+        recorder.record("pwd", vec!["pwd".to_string()], "/root".to_string(), 0, 1, "/root\n");
+        recorder.record("whoami", vec!["whoami".to_string()], "/root".to_string(), 0, 1, "root\n");
+
+        assert_eq!(recorder.events().len(), 2);
+    }
+
+    #[test]
+    fn test_monotonic_ms_is_nondecreasing_across_events() {
+        let mut recorder = Recorder::with_sink(Box::new(Vec::new()));
+        recorder.record("a", vec![], "/root".to_string(), 0, 0, "");
+        recorder.record("b", vec![], "/root".to_string(), 0, 0, "");
+
+        let events = recorder.events();
+        assert!(events[1].monotonic_ms >= events[0].monotonic_ms);
+    }
+}